@@ -0,0 +1,40 @@
+//! SDP daemon: serves the JSON-RPC protocol over stdio or a Unix domain socket.
+//!
+//! Usage: `sdpd [--root <dir>] [--socket <path>]`
+//!
+//! History is stored under `--root` (default `.sdp`).
+
+use semantic_delta_protocol::server::Server;
+use semantic_delta_protocol::{Config, Engine, SrpResult};
+use std::path::PathBuf;
+
+const USAGE: &str = "usage: sdpd [--root <dir>] [--socket <path>]";
+
+fn main() -> SrpResult<()> {
+    let mut config = Config::default();
+    let mut socket: Option<PathBuf> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--root", Some(root)) => config.root = PathBuf::from(root),
+            ("--socket", Some(path)) => socket = Some(PathBuf::from(path)),
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let server = Server::with_engine(Engine::new(config)?)?;
+    match socket {
+        None => server.serve_stdio(),
+        #[cfg(unix)]
+        Some(path) => server.serve_unix(path),
+        #[cfg(not(unix))]
+        Some(_) => {
+            eprintln!("--socket is only supported on Unix");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use crate::error::SrpResult;
+use crate::models::{ReferenceKind, SemanticSymbol, SymbolReference};
+use crate::project::ProjectTree;
+use crate::semantic::SemanticParser;
+use crate::semantic::diff::{SemanticDiffer, canonical};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Symbol kinds a call can land on.
+const CALLABLE_KINDS: &[&str] = &["function", "method", "struct", "class", "variant"];
+
+/// Kinds whose nested callables are reached through a type path or a value.
+const TYPE_KINDS: &[&str] = &["impl", "trait", "struct", "enum", "class", "interface"];
+
+/// Path segments that name a module relative to the current one.
+const RELATIVE_SEGMENTS: &[&str] = &["crate", "self", "super"];
+
+/// One file's symbols and references, as from
+/// `SemanticParser::parse_semantic_data`.
+#[derive(Clone, Debug, Default)]
+pub struct SourceFile {
+    pub path: String,
+    pub symbols: Vec<SemanticSymbol>,
+    pub references: Vec<SymbolReference>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallNode {
+    pub path: String,
+    pub name: String,
+    pub scope: Option<String>,
+    pub kind: String,
+    pub start_line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    /// Indices into `CallGraph::nodes`
+    pub caller: usize,
+    pub callee: usize,
+    /// Line of the call in the caller's file
+    pub line: usize,
+    /// Set when several symbols fit the call equally well; each gets an edge
+    pub ambiguous: bool,
+}
+
+/// A call no project symbol fits, such as one into a library.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedCall {
+    pub caller: usize,
+    /// The called name, with an import alias replaced by the imported name
+    pub name: String,
+    pub qualifier: Option<String>,
+    /// Where the call looks for `name` once imports are followed: the last
+    /// segment of its path (`util` in `util::tax()`), or the module a plain
+    /// imported name came from; unset for method calls
+    #[serde(default)]
+    pub module: Option<String>,
+    pub line: usize,
+}
+
+/// Who calls whom across a project. Every symbol is a node, in file order
+/// and then canonical order within a file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CallGraph {
+    pub nodes: Vec<CallNode>,
+    pub edges: Vec<CallEdge>,
+    pub unresolved: Vec<UnresolvedCall>,
+}
+
+/// What resolution needs to know about a node besides `CallNode`.
+struct NodeInfo {
+    file: usize,
+    /// Nested in a type, so only reachable through a type path or a value
+    in_type: bool,
+}
+
+impl CallGraph {
+    /// Builds the graph from parsed files.
+    ///
+    /// Each call is attributed to the innermost symbol around it (calls
+    /// outside any symbol are dropped) and resolved among the project's
+    /// callable symbols of that name:
+    /// - `value.name()` only reaches methods, preferring the caller's own
+    ///   type for `self.name()`;
+    /// - `path::name()` reaches methods of a type whose name ends the path
+    ///   (`Self` is the caller's type) or functions in a module or file of
+    ///   that name, following `use` imports of the path's first segment;
+    /// - `name()` reaches free functions, preferring the caller's file, then
+    ///   the module it was imported from, then any other file.
+    ///
+    /// The best fit wins; ties give ambiguous edges to each.
+    pub fn build(files: &[SourceFile]) -> Self {
+        let mut graph = Self::default();
+        let mut info = Vec::new();
+        let mut sorted = Vec::with_capacity(files.len());
+        for (f, file) in files.iter().enumerate() {
+            let symbols = canonical(&file.symbols).into_owned();
+            let parents = SemanticDiffer::parents(&symbols);
+            for (i, symbol) in symbols.iter().enumerate() {
+                graph.nodes.push(CallNode {
+                    path: file.path.clone(),
+                    name: symbol.name.clone(),
+                    scope: symbol.scope.clone(),
+                    kind: symbol.kind.clone(),
+                    start_line: symbol.start_line,
+                });
+                info.push(NodeInfo {
+                    file: f,
+                    in_type: parents[i]
+                        .is_some_and(|p| TYPE_KINDS.contains(&symbols[p].kind.as_str())),
+                });
+            }
+            sorted.push(symbols);
+        }
+
+        let mut callable: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (n, node) in graph.nodes.iter().enumerate() {
+            if CALLABLE_KINDS.contains(&node.kind.as_str()) {
+                callable.entry(node.name.as_str()).or_default().push(n);
+            }
+        }
+        let modules: Vec<String> = files.iter().map(|f| module_of(&f.path)).collect();
+
+        let mut first_node = 0;
+        let mut edges = Vec::new();
+        let mut unresolved = Vec::new();
+        for (f, file) in files.iter().enumerate() {
+            let symbols = &sorted[f];
+            // local name -> imported path
+            let imports: HashMap<&str, &str> = file
+                .references
+                .iter()
+                .filter(|r| r.kind == ReferenceKind::Import)
+                .filter_map(|r| Some((r.symbol_name.as_str(), r.qualifier.as_deref()?)))
+                .collect();
+
+            for reference in &file.references {
+                if reference.kind == ReferenceKind::Import {
+                    continue;
+                }
+                // Canonical order puts outer symbols first, so the last
+                // containing symbol is the innermost.
+                let Some(caller) = symbols
+                    .iter()
+                    .rposition(|s| {
+                        s.start_byte <= reference.start_byte && reference.start_byte < s.end_byte
+                    })
+                    .map(|i| first_node + i)
+                else {
+                    continue;
+                };
+                let caller_node = &graph.nodes[caller];
+
+                let target = Target::of(reference, &imports, caller_node);
+                let scores: Vec<(usize, u8)> = callable
+                    .get(target.name.as_str())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&n| {
+                        let candidate = &graph.nodes[n];
+                        let fits_module = |module: &str| {
+                            modules[info[n].file] == module
+                                || last_segment(&candidate.scope) == Some(module)
+                        };
+                        let same_file = info[n].file == f;
+                        let score = match (&reference.kind, &target.path) {
+                            (ReferenceKind::MethodCall, _) => {
+                                if !info[n].in_type {
+                                    return None;
+                                }
+                                if reference.qualifier.as_deref() == Some("self")
+                                    && candidate.scope == caller_node.scope
+                                {
+                                    3
+                                } else if same_file {
+                                    2
+                                } else {
+                                    1
+                                }
+                            }
+                            (_, Some(path)) => {
+                                let last = path.rsplit("::").next().unwrap_or(path);
+                                if info[n].in_type {
+                                    if last_segment(&candidate.scope) != Some(last) {
+                                        return None;
+                                    }
+                                    4
+                                } else if fits_module(last) {
+                                    3
+                                } else if RELATIVE_SEGMENTS.contains(&last) {
+                                    if same_file { 2 } else { 1 }
+                                } else {
+                                    return None;
+                                }
+                            }
+                            (_, None) => {
+                                if info[n].in_type {
+                                    return None;
+                                }
+                                match target.module.as_deref() {
+                                    Some(module) if fits_module(module) => 3,
+                                    Some(_) => 2,
+                                    None if same_file => {
+                                        3 + u8::from(candidate.scope == caller_node.scope)
+                                    }
+                                    None => 1,
+                                }
+                            }
+                        };
+                        Some((n, score))
+                    })
+                    .collect();
+
+                let best = scores.iter().map(|&(_, s)| s).max();
+                let chosen: Vec<usize> = scores
+                    .iter()
+                    .filter(|&&(_, s)| Some(s) == best)
+                    .map(|&(n, _)| n)
+                    .collect();
+                if chosen.is_empty() {
+                    let module = match &target.path {
+                        Some(path) => path.rsplit("::").next().map(str::to_string),
+                        None => target.module.clone(),
+                    };
+                    unresolved.push(UnresolvedCall {
+                        caller,
+                        name: target.name.clone(),
+                        qualifier: reference.qualifier.clone(),
+                        module,
+                        line: reference.start_line,
+                    });
+                }
+                for &callee in &chosen {
+                    edges.push(CallEdge {
+                        caller,
+                        callee,
+                        line: reference.start_line,
+                        ambiguous: chosen.len() > 1,
+                    });
+                }
+            }
+            first_node += symbols.len();
+        }
+
+        graph.edges = edges;
+        graph.unresolved = unresolved;
+        graph
+    }
+
+    /// Parses every file of `tree` in parallel and builds the graph.
+    pub fn from_tree(tree: &ProjectTree) -> SrpResult<Self> {
+        let files = tree
+            .files
+            .par_iter()
+            .map_init(SemanticParser::new, |parser, (path, content)| {
+                let parser = parser.as_mut().map_err(|e| {
+                    crate::error::SrpError::Internal(format!("Parser setup failed: {}", e))
+                })?;
+                let extension = Path::new(path)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("");
+                let (symbols, references) =
+                    parser.parse_semantic_data(content, extension, 0, None)?;
+                Ok(SourceFile {
+                    path: path.clone(),
+                    symbols,
+                    references,
+                })
+            })
+            .collect::<SrpResult<Vec<_>>>()?;
+        Ok(Self::build(&files))
+    }
+
+    /// Nodes named `name`, optionally only in `path`.
+    pub fn find(&self, name: &str, path: Option<&str>) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&n| self.nodes[n].name == name && path.is_none_or(|p| self.nodes[n].path == p))
+            .collect()
+    }
+
+    /// Nodes with a call to `node`, in node order.
+    pub fn callers_of(&self, node: usize) -> Vec<usize> {
+        let callers: BTreeSet<usize> = self
+            .edges
+            .iter()
+            .filter(|e| e.callee == node)
+            .map(|e| e.caller)
+            .collect();
+        callers.into_iter().collect()
+    }
+
+    /// Nodes `node` calls, in node order.
+    pub fn callees_of(&self, node: usize) -> Vec<usize> {
+        let callees: BTreeSet<usize> = self
+            .edges
+            .iter()
+            .filter(|e| e.caller == node)
+            .map(|e| e.callee)
+            .collect();
+        callees.into_iter().collect()
+    }
+
+    /// Graphviz rendering of the symbols that take part in calls, one edge
+    /// per caller and callee; ambiguous edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut pairs: Vec<(usize, usize, bool)> = self
+            .edges
+            .iter()
+            .map(|e| (e.caller, e.callee, e.ambiguous))
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup_by_key(|&mut (caller, callee, _)| (caller, callee));
+        let used: BTreeSet<usize> = pairs.iter().flat_map(|&(a, b, _)| [a, b]).collect();
+
+        let mut dot = String::from("digraph calls {\n");
+        for n in used {
+            let node = &self.nodes[n];
+            let label = match &node.scope {
+                Some(scope) => format!("{}::{}\\n{}", scope, node.name, node.path),
+                None => format!("{}\\n{}", node.name, node.path),
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}\"];",
+                n,
+                label.replace('"', "\\\"")
+            );
+        }
+        for (caller, callee, ambiguous) in pairs {
+            let style = if ambiguous { " [style=dashed]" } else { "" };
+            let _ = writeln!(dot, "    n{} -> n{}{};", caller, callee, style);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> SrpResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The name a reference calls and where to look for it, after imports.
+struct Target {
+    name: String,
+    /// `path::` before the name, with an imported first segment expanded and
+    /// `Self` replaced by the caller's type
+    path: Option<String>,
+    /// For a plain imported name, the module it was imported from
+    module: Option<String>,
+}
+
+impl Target {
+    fn of(reference: &SymbolReference, imports: &HashMap<&str, &str>, caller: &CallNode) -> Self {
+        let name = reference.symbol_name.clone();
+        match (&reference.kind, &reference.qualifier) {
+            (ReferenceKind::Call, Some(path)) => {
+                let (first, rest) = match path.split_once("::") {
+                    Some((first, rest)) => (first, Some(rest)),
+                    None => (path.as_str(), None),
+                };
+                let first = match first {
+                    "Self" => caller
+                        .scope
+                        .as_deref()
+                        .and_then(|s| s.rsplit("::").next())
+                        .unwrap_or(first),
+                    _ => imports.get(first).copied().unwrap_or(first),
+                };
+                let path = match rest {
+                    Some(rest) => format!("{}::{}", first, rest),
+                    None => first.to_string(),
+                };
+                Self {
+                    name,
+                    path: Some(path),
+                    module: None,
+                }
+            }
+            (ReferenceKind::Call, None) => match imports.get(name.as_str()) {
+                Some(imported) => {
+                    let mut segments = imported.rsplit("::");
+                    let original = segments.next().unwrap_or(imported).to_string();
+                    Self {
+                        name: original,
+                        path: None,
+                        module: segments.next().map(str::to_string),
+                    }
+                }
+                None => Self {
+                    name,
+                    path: None,
+                    module: None,
+                },
+            },
+            _ => Self {
+                name,
+                path: None,
+                module: None,
+            },
+        }
+    }
+}
+
+fn last_segment(scope: &Option<String>) -> Option<&str> {
+    scope.as_deref().and_then(|s| s.rsplit("::").next())
+}
+
+/// The module a file defines: its stem, or its directory's name for
+/// `mod.rs`, `lib.rs` and the like.
+pub(crate) fn module_of(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    if matches!(stem, "mod" | "lib" | "main" | "__init__" | "index") {
+        if let Some(dir) = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|d| d.to_str())
+        {
+            return dir.to_string();
+        }
+    }
+    stem.to_string()
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests {
+    use crate::callgraph::CallGraph;
+    use crate::models::ReferenceKind;
+    use crate::project::ProjectTree;
+    use crate::semantic::SemanticParser;
+    use crate::test_util;
+    use bytes::Bytes;
+
+    const MAIN: &str = "use crate::util::helper;\nuse crate::config::Config;\n\nfn main() {\n    let config = Config::new();\n    helper();\n    config.validate();\n    util::parse();\n}\n";
+    const CONFIG: &str = "pub struct Config {\n    port: u16,\n}\n\nimpl Config {\n    pub fn new() -> Self {\n        Self::defaults()\n    }\n\n    fn defaults() -> Self {\n        Config { port: helper_port() }\n    }\n}\n\nfn helper_port() -> u16 {\n    80\n}\n";
+    const UTIL: &str = "pub fn helper() -> u32 {\n    1\n}\n\npub fn parse() {}\n";
+    const CLI: &str = "pub fn parse() {}\n";
+    const RUN: &str = "fn run() {\n    parse();\n}\n";
+
+    fn graph() -> CallGraph {
+        let mut tree = ProjectTree::default();
+        for (path, content) in [
+            ("src/main.rs", MAIN),
+            ("src/config.rs", CONFIG),
+            ("src/util.rs", UTIL),
+            ("src/cli.rs", CLI),
+            ("src/run.rs", RUN),
+        ] {
+            tree.files
+                .insert(path.to_string(), Bytes::from(content.to_string()));
+        }
+        CallGraph::from_tree(&tree).unwrap()
+    }
+
+    fn node(graph: &CallGraph, name: &str, path: &str) -> usize {
+        let found = graph.find(name, Some(path));
+        assert_eq!(found.len(), 1, "{} in {}", name, path);
+        found[0]
+    }
+
+    fn names(graph: &CallGraph, nodes: Vec<usize>) -> Vec<(String, String)> {
+        nodes
+            .into_iter()
+            .map(|n| (graph.nodes[n].path.clone(), graph.nodes[n].name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parser_names_calls_and_imports() {
+        let source = "use std::{fs, io::Read as R};\nfn f() { g(); x.run(); Self::new(); }";
+        let (_, references) = SemanticParser::new()
+            .unwrap()
+            .parse_semantic_data(&Bytes::from(source), "rs", 0, None)
+            .unwrap();
+
+        let found: Vec<(ReferenceKind, &str, Option<&str>)> = references
+            .iter()
+            .map(|r| {
+                (
+                    r.kind.clone(),
+                    r.symbol_name.as_str(),
+                    r.qualifier.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ReferenceKind::Import, "fs", Some("std::fs")),
+                (ReferenceKind::Import, "R", Some("std::io::Read")),
+                (ReferenceKind::Call, "g", None),
+                (ReferenceKind::MethodCall, "run", Some("x")),
+                (ReferenceKind::Call, "new", Some("Self")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_graph_resolves_through_scope_and_imports() {
+        let graph = graph();
+        let main = node(&graph, "main", "src/main.rs");
+
+        assert_eq!(
+            names(&graph, graph.callees_of(main)),
+            vec![
+                ("src/config.rs".to_string(), "new".to_string()),
+                ("src/util.rs".to_string(), "helper".to_string()),
+                ("src/util.rs".to_string(), "parse".to_string()),
+            ]
+        );
+        let new = node(&graph, "new", "src/config.rs");
+        assert_eq!(
+            names(&graph, graph.callees_of(new)),
+            vec![("src/config.rs".to_string(), "defaults".to_string())]
+        );
+        let helper_port = node(&graph, "helper_port", "src/config.rs");
+        assert_eq!(
+            graph.callers_of(helper_port),
+            vec![node(&graph, "defaults", "src/config.rs")]
+        );
+
+        assert_eq!(graph.unresolved.len(), 1);
+        assert_eq!(graph.unresolved[0].name, "validate");
+        assert_eq!(graph.unresolved[0].caller, main);
+    }
+
+    #[test]
+    fn test_call_graph_marks_ambiguous_calls() {
+        let graph = graph();
+        let run = node(&graph, "run", "src/run.rs");
+
+        let edges: Vec<_> = graph.edges.iter().filter(|e| e.caller == run).collect();
+        assert_eq!(edges.len(), 2);
+        assert!(edges.iter().all(|e| e.ambiguous));
+        assert_eq!(
+            graph.callers_of(node(&graph, "parse", "src/cli.rs")),
+            vec![run]
+        );
+    }
+
+    #[test]
+    fn test_call_graph_exports() {
+        let graph = graph();
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph calls {\n"));
+        let main = node(&graph, "main", "src/main.rs");
+        let helper = node(&graph, "helper", "src/util.rs");
+        assert!(dot.contains(&format!("    n{} -> n{};\n", main, helper)));
+        assert!(dot.contains("[label=\"Config::new\\nsrc/config.rs\"]"));
+        assert!(dot.contains("[style=dashed]"));
+
+        let parsed: CallGraph = serde_json::from_str(&graph.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.nodes, graph.nodes);
+        assert_eq!(parsed.edges, graph.edges);
+    }
+
+    #[test]
+    fn test_engine_builds_call_graph_from_saved_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+        engine.save("src/main.rs", MAIN).unwrap();
+        engine.save("src/util.rs", UTIL).unwrap();
+
+        let graph = engine.call_graph().unwrap();
+
+        let helper = node(&graph, "helper", "src/util.rs");
+        assert_eq!(
+            graph.callers_of(helper),
+            vec![node(&graph, "main", "src/main.rs")]
+        );
+    }
+}
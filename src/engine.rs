@@ -0,0 +1,463 @@
+use crate::callgraph::{CallGraph, SourceFile};
+use crate::error::{SrpError, SrpResult};
+use crate::history;
+use crate::impact::{self, Impact, ImpactOptions};
+use crate::models::{ChangeFlag, RecordKind, SemanticRecord, SemanticSymbol, Snapshot};
+use crate::patch::{self, SemanticPatch};
+use crate::project::{self, ProjectDiff, ProjectTree};
+use crate::protocol::{HistoryParams, HistoryResult, SearchParams, SearchResult};
+use crate::search;
+use crate::semantic::SemanticParser;
+use crate::semantic::diff::{DiffOptions, SemanticDiffer};
+use crate::semantic::tree_diff::diff_nodes;
+use crate::storage::cas::CasStore;
+use crate::storage::manifest::{FileManifest, reassemble};
+use crate::storage::symbols::{SnapshotData, SymbolRegistry};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Engine configuration.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Protocol data directory; holds `db/symbols.db` and `cas/`.
+    pub root: PathBuf,
+    /// Copied into every `SemanticRecord` produced by this engine.
+    pub project_id: Option<String>,
+    /// Rename and move detection thresholds used by `save`.
+    pub diff: DiffOptions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from(".sdp"),
+            project_id: None,
+            diff: DiffOptions::default(),
+        }
+    }
+}
+
+/// High-level entry point that runs the full save pipeline:
+/// parse, fingerprint, diff against the previous snapshot, chunk, store and record.
+pub struct Engine {
+    config: Config,
+    parser: Mutex<SemanticParser>,
+    cas: CasStore,
+    registry: SymbolRegistry,
+}
+
+impl Engine {
+    pub fn new(config: Config) -> SrpResult<Self> {
+        let cas = CasStore::open(config.root.join("cas"))?;
+        let registry = SymbolRegistry::open(config.root.join("db").join("symbols.db"))?;
+
+        Ok(Self {
+            config,
+            parser: Mutex::new(SemanticParser::new()?),
+            cas,
+            registry,
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn cas(&self) -> &CasStore {
+        &self.cas
+    }
+
+    pub fn registry(&self) -> &SymbolRegistry {
+        &self.registry
+    }
+
+    /// Saves a new version of `path` and returns the semantic records it produced.
+    ///
+    /// Saving content identical to the latest snapshot is a no-op and returns no records.
+    pub fn save(&self, path: &str, content: impl Into<Bytes>) -> SrpResult<Vec<SemanticRecord>> {
+        let content = content.into();
+        let extension = extension_of(path);
+
+        let previous = self.registry.latest_snapshot(path)?;
+        if let Some(prev) = &previous {
+            if prev.file_hash == CasStore::hash(&content) {
+                return Ok(vec![]);
+            }
+        }
+
+        // No file path: the parser's cached tree has not been edited to match the
+        // new content, so reusing it for incremental parsing would be unsound.
+        let (mut symbols, references) = self
+            .parser()?
+            .parse_semantic_data(&content, extension, 0, None)?;
+
+        let manifest = FileManifest::store(&self.cas, path, 0, content.clone(), extension)?;
+        assign_chunk_hashes(&mut symbols, &manifest);
+
+        let (from_snapshot_id, prev_symbols) = match &previous {
+            Some(prev) => (Some(prev.id), self.registry.symbols_in_snapshot(prev.id)?),
+            None => (None, vec![]),
+        };
+
+        let mut records = SemanticDiffer::compare_with(
+            &prev_symbols,
+            &symbols,
+            from_snapshot_id,
+            0,
+            &self.config.diff,
+        );
+        for record in records.iter_mut() {
+            record.project_id = self.config.project_id.clone();
+            record.path = Some(path.to_string());
+        }
+        if let Some(prev) = &previous {
+            self.attach_node_changes(
+                &mut records,
+                prev.id,
+                &prev_symbols,
+                &symbols,
+                &content,
+                extension,
+            )?;
+        }
+
+        let file_hash = manifest.file_hash.clone();
+        let mut data = SnapshotData {
+            symbols,
+            references,
+            records,
+            manifest: Some(manifest),
+        };
+        self.registry.commit_snapshot(path, &file_hash, &mut data)?;
+
+        Ok(data.records)
+    }
+
+    /// Resolves which snapshot of `path` to use: `snapshot_id` if given, otherwise
+    /// the latest snapshot taken at or before `at`, otherwise the latest snapshot.
+    pub fn find_snapshot(
+        &self,
+        path: &str,
+        snapshot_id: Option<i64>,
+        at: Option<DateTime<Utc>>,
+    ) -> SrpResult<Snapshot> {
+        let snapshots = self.registry.snapshots_for_path(path)?;
+        let found = match (snapshot_id, at) {
+            (Some(id), _) => snapshots.into_iter().find(|s| s.id == id),
+            (None, Some(at)) => snapshots.into_iter().rev().find(|s| s.timestamp <= at),
+            (None, None) => snapshots.into_iter().last(),
+        };
+
+        found.ok_or_else(|| match (snapshot_id, at) {
+            (Some(id), _) => SrpError::NotFound(format!("{}: no snapshot {}", path, id)),
+            (None, Some(at)) => {
+                SrpError::NotFound(format!("{}: no snapshot at or before {}", path, at))
+            }
+            (None, None) => SrpError::NotFound(format!("{}: no snapshots", path)),
+        })
+    }
+
+    /// The project as stored: every saved path at its latest snapshot taken
+    /// at or before `at` (or its latest one). Paths first saved after `at`
+    /// are left out.
+    pub fn project_tree(&self, at: Option<DateTime<Utc>>) -> SrpResult<ProjectTree> {
+        let mut tree = ProjectTree::default();
+        for path in self.registry.paths()? {
+            let snapshot = match self.find_snapshot(&path, None, at) {
+                Ok(snapshot) => snapshot,
+                Err(SrpError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            tree.files.insert(path, self.read_snapshot(snapshot.id)?);
+        }
+        Ok(tree)
+    }
+
+    /// Diffs two versions of a project with this engine's `DiffOptions`; see
+    /// `project::diff_trees`. Use `ProjectTree::read_dir` for a directory and
+    /// `project_tree` for stored snapshots.
+    pub fn diff_project(&self, old: &ProjectTree, new: &ProjectTree) -> SrpResult<ProjectDiff> {
+        let mut diff = project::diff_trees(old, new, None, 0, &self.config.diff)?;
+        let records = diff
+            .files
+            .iter_mut()
+            .flat_map(|f| f.records.iter_mut())
+            .chain(diff.moves.iter_mut());
+        for record in records {
+            record.project_id = self.config.project_id.clone();
+        }
+        Ok(diff)
+    }
+
+    /// The patch turning `path` as of `from_snapshot_id` into its content in
+    /// `to_snapshot_id`, with texts stored in the CAS; see `patch::create`.
+    pub fn semantic_patch(
+        &self,
+        path: &str,
+        from_snapshot_id: i64,
+        to_snapshot_id: i64,
+    ) -> SrpResult<SemanticPatch> {
+        let old = self.read_snapshot(from_snapshot_id)?;
+        let new = self.read_snapshot(to_snapshot_id)?;
+        let mut patch = patch::create(extension_of(path), &old, &new)?;
+        patch.store_texts(&self.cas)?;
+        Ok(patch)
+    }
+
+    /// Applies `patch` to `content`, reading chunk references from the CAS;
+    /// see `patch::apply`.
+    pub fn apply_patch(&self, content: &[u8], patch: &SemanticPatch) -> SrpResult<Bytes> {
+        let mut patch = patch.clone();
+        patch.load_texts(&self.cas)?;
+        patch::apply(content, &patch)
+    }
+
+    /// The call graph of the project as last saved, from the stored symbols
+    /// and references; see `CallGraph::build`.
+    pub fn call_graph(&self) -> SrpResult<CallGraph> {
+        let mut files = Vec::new();
+        for path in self.registry.paths()? {
+            let Some(snapshot) = self.registry.latest_snapshot(&path)? else {
+                continue;
+            };
+            files.push(SourceFile {
+                symbols: self.registry.symbols_in_snapshot(snapshot.id)?,
+                references: self.registry.references_in_snapshot(snapshot.id)?,
+                path,
+            });
+        }
+        Ok(CallGraph::build(&files))
+    }
+
+    /// What `records` affect in the project as last saved; see
+    /// `impact::impact`.
+    pub fn impact(&self, records: &[SemanticRecord], options: &ImpactOptions) -> SrpResult<Impact> {
+        Ok(impact::impact(&self.call_graph()?, records, options))
+    }
+
+    /// Rebuilds the file content stored in `snapshot_id` from its chunks.
+    pub fn read_snapshot(&self, snapshot_id: i64) -> SrpResult<Bytes> {
+        let manifest = self.registry.get_manifest(snapshot_id)?.ok_or_else(|| {
+            SrpError::NotFound(format!("No manifest for snapshot {}", snapshot_id))
+        })?;
+        reassemble(&self.cas, &manifest)
+    }
+
+    /// Overwrites the working file at `path` with its content from `snapshot_id`.
+    pub fn restore(&self, path: &str, snapshot_id: i64) -> SrpResult<Bytes> {
+        let content = self.read_snapshot(snapshot_id)?;
+        std::fs::write(path, &content)?;
+        Ok(content)
+    }
+
+    /// Replaces the current body of symbol `name` (in `scope`) inside `current`
+    /// with its text from `snapshot_id`, leaving the rest of the file untouched.
+    ///
+    /// `kind` tells apart symbols sharing a name and scope, such as a Rust
+    /// `struct Foo` and its `impl Foo`; the current symbol is always matched
+    /// on the kind of the restored one. When several symbols still match on
+    /// either side, the splice is refused as ambiguous. The result must still
+    /// parse cleanly, otherwise nothing is returned.
+    pub fn splice_symbol(
+        &self,
+        path: &str,
+        current: &[u8],
+        name: &str,
+        scope: Option<&str>,
+        kind: Option<&str>,
+        snapshot_id: i64,
+    ) -> SrpResult<Bytes> {
+        let extension = extension_of(path);
+        let is_target = |s: &SemanticSymbol, kind: Option<&str>| {
+            s.name == name && s.scope.as_deref() == scope && kind.is_none_or(|k| s.kind == k)
+        };
+        let describe = || match scope {
+            Some(scope) => format!("{}::{}", scope, name),
+            None => name.to_string(),
+        };
+        let single = |mut found: Vec<SemanticSymbol>, place: String| match found.len() {
+            0 => Err(SrpError::NotFound(format!(
+                "{} not in {}",
+                describe(),
+                place
+            ))),
+            1 => Ok(found.remove(0)),
+            _ => Err(SrpError::Analysis(format!(
+                "{} is ambiguous in {}: {} symbols match ({})",
+                describe(),
+                place,
+                found.len(),
+                found
+                    .iter()
+                    .map(|s| s.kind.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        };
+
+        let old = single(
+            self.registry
+                .symbols_in_snapshot(snapshot_id)?
+                .into_iter()
+                .filter(|s| is_target(s, kind))
+                .collect(),
+            format!("snapshot {}", snapshot_id),
+        )?;
+        let old_content = self.read_snapshot(snapshot_id)?;
+        let old_text = old_content
+            .get(old.start_byte..old.end_byte)
+            .ok_or_else(|| SrpError::Integrity(format!("{} range out of bounds", describe())))?;
+
+        let current_bytes = Bytes::copy_from_slice(current);
+        let mut parser = self.parser()?;
+        let (symbols, _) = parser.parse_semantic_data(&current_bytes, extension, 0, None)?;
+        let target = single(
+            symbols
+                .into_iter()
+                .filter(|s| is_target(s, Some(&old.kind)))
+                .collect(),
+            path.to_string(),
+        )?;
+
+        let mut spliced = Vec::with_capacity(current.len() + old_text.len());
+        spliced.extend_from_slice(&current[..target.start_byte]);
+        spliced.extend_from_slice(old_text);
+        spliced.extend_from_slice(&current[target.end_byte..]);
+
+        if parser.has_syntax_errors(&spliced, extension)? {
+            return Err(SrpError::Parse(format!(
+                "Restoring {} from snapshot {} leaves {} with syntax errors",
+                describe(),
+                snapshot_id,
+                path
+            )));
+        }
+
+        Ok(Bytes::from(spliced))
+    }
+
+    /// Rolls back a single symbol in the working file at `path` to its version
+    /// in `snapshot_id`. See `splice_symbol`.
+    pub fn restore_symbol(
+        &self,
+        path: &str,
+        name: &str,
+        scope: Option<&str>,
+        kind: Option<&str>,
+        snapshot_id: i64,
+    ) -> SrpResult<Bytes> {
+        let current = std::fs::read(path)?;
+        let restored = self.splice_symbol(path, &current, name, scope, kind, snapshot_id)?;
+        std::fs::write(path, &restored)?;
+        Ok(restored)
+    }
+
+    /// Returns every recorded change to `symbol` in `path`, oldest first,
+    /// following renames.
+    pub fn get_symbol_history(&self, path: &str, symbol: &str) -> SrpResult<HistoryResult> {
+        self.history(&HistoryParams {
+            path: path.to_string(),
+            symbol: symbol.to_string(),
+            scope: None,
+        })
+    }
+
+    /// Scope-aware form of `get_symbol_history`; see `history::symbol_history`.
+    pub fn history(&self, params: &HistoryParams) -> SrpResult<HistoryResult> {
+        history::symbol_history(&self.registry, params)
+    }
+
+    /// Searches the stored history; see `search::search`.
+    pub fn search(&self, params: &SearchParams) -> SrpResult<SearchResult> {
+        search::search(&self.registry, params)
+    }
+
+    /// Fills in `changes` on every `Modified` record by diffing the symbol's
+    /// syntax tree against its version in `prev_snapshot_id`. Parents changed
+    /// only through their children are skipped; the children carry the edits.
+    fn attach_node_changes(
+        &self,
+        records: &mut [SemanticRecord],
+        prev_snapshot_id: i64,
+        prev_symbols: &[SemanticSymbol],
+        symbols: &[SemanticSymbol],
+        content: &[u8],
+        extension: &str,
+    ) -> SrpResult<()> {
+        if !records.iter().any(|r| r.kind == RecordKind::Modified) {
+            return Ok(());
+        }
+        let Some(manifest) = self.registry.get_manifest(prev_snapshot_id)? else {
+            return Ok(());
+        };
+        let prev_content = reassemble(&self.cas, &manifest)?;
+
+        let mut parser = self.parser()?;
+        let (Some(old_tree), Some(new_tree)) = (
+            parser.parse_tree(&prev_content, extension)?,
+            parser.parse_tree(content, extension)?,
+        ) else {
+            return Ok(());
+        };
+
+        let mut pairs: Vec<(usize, usize)> =
+            SemanticDiffer::match_by_identity(prev_symbols, symbols)
+                .into_iter()
+                .collect();
+        pairs.sort_unstable();
+        for (c, p) in pairs {
+            let (old, new) = (&prev_symbols[p], &symbols[c]);
+            if old.structural_hash == new.structural_hash && old.hashes == new.hashes {
+                continue;
+            }
+            let Some(record) = records.iter_mut().find(|r| {
+                r.kind == RecordKind::Modified
+                    && r.changes.is_empty()
+                    && r.flags != [ChangeFlag::ChildrenChanged]
+                    && r.symbol_name == new.name
+                    && r.scope == new.scope
+                    && r.structural_hash == new.structural_hash
+            }) else {
+                continue;
+            };
+            let old_node = old_tree
+                .root_node()
+                .descendant_for_byte_range(old.start_byte, old.end_byte);
+            let new_node = new_tree
+                .root_node()
+                .descendant_for_byte_range(new.start_byte, new.end_byte);
+            if let (Some(old_node), Some(new_node)) = (old_node, new_node) {
+                record.changes = diff_nodes(old_node, &prev_content, new_node, content);
+            }
+        }
+        Ok(())
+    }
+
+    fn parser(&self) -> SrpResult<MutexGuard<'_, SemanticParser>> {
+        self.parser
+            .lock()
+            .map_err(|_| SrpError::Internal("Parser lock poisoned".into()))
+    }
+}
+
+/// Points each symbol at the chunk holding its first byte.
+fn assign_chunk_hashes(symbols: &mut [SemanticSymbol], manifest: &FileManifest) {
+    for symbol in symbols.iter_mut() {
+        if let Some(chunk) = manifest
+            .chunks
+            .iter()
+            .find(|c| symbol.start_byte >= c.offset && symbol.start_byte < c.offset + c.length)
+        {
+            symbol.chunk_hash = chunk.hash.clone();
+        }
+    }
+}
+
+fn extension_of(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::{Config, Engine};
+    use crate::error::SrpError;
+    use crate::models::{NodeChangeKind, RecordKind};
+    use crate::storage::manifest::reassemble;
+
+    fn engine(dir: &tempfile::TempDir) -> Engine {
+        Engine::new(Config {
+            root: dir.path().join(".sdp"),
+            project_id: Some("demo".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    const V1: &str = "fn calculate_total(items: Vec<f64>) -> f64 {\n    items.iter().sum()\n}\n\nfn helper() {}\n";
+    const V2: &str = "fn calculate_total(items: Vec<f64>) -> f64 {\n    let total = items.iter().sum();\n    total\n}\n\nfn helper() {}\n";
+
+    #[test]
+    fn test_first_save_adds_every_symbol() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+
+        let records = engine.save("src/main.rs", V1).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| matches!(r.kind, RecordKind::Added)));
+        assert!(records.iter().all(|r| r.from_snapshot_id.is_none()));
+        assert!(
+            records
+                .iter()
+                .all(|r| r.project_id.as_deref() == Some("demo"))
+        );
+        assert!(dir.path().join(".sdp/db/symbols.db").exists());
+    }
+
+    #[test]
+    fn test_second_save_diffs_against_previous_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+
+        engine.save("src/main.rs", V1).unwrap();
+        let records = engine.save("src/main.rs", V2).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert!(matches!(records[0].kind, RecordKind::Modified));
+        assert_eq!(records[0].symbol_name, "calculate_total");
+        assert_eq!(records[0].from_snapshot_id, Some(1));
+        assert_eq!(records[0].to_snapshot_id, 2);
+        assert!(records[0].changes.iter().any(|c| {
+            c.kind == NodeChangeKind::Inserted
+                && c.node_kind == "let_declaration"
+                && c.new_text.as_deref() == Some("let total = items.iter().sum();")
+        }));
+
+        // Unchanged content does not create a snapshot.
+        assert!(engine.save("src/main.rs", V2).unwrap().is_empty());
+        assert_eq!(
+            engine
+                .registry()
+                .snapshots_for_path("src/main.rs")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_symbol_history_and_restore_from_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+
+        engine.save("src/main.rs", V1).unwrap();
+        engine.save("src/main.rs", V2).unwrap();
+        engine.save("src/other.rs", V1).unwrap();
+
+        let history = engine
+            .get_symbol_history("src/main.rs", "calculate_total")
+            .unwrap();
+        assert_eq!(history.versions.len(), 2);
+        assert!(matches!(history.versions[0].record.kind, RecordKind::Added));
+        assert!(matches!(
+            history.versions[1].record.kind,
+            RecordKind::Modified
+        ));
+
+        let symbols = engine.registry().symbols_in_snapshot(1).unwrap();
+        assert!(symbols.iter().all(|s| !s.chunk_hash.is_empty()));
+
+        let manifest = engine.registry().get_manifest(1).unwrap().unwrap();
+        assert_eq!(reassemble(engine.cas(), &manifest).unwrap(), V1.as_bytes());
+    }
+
+    #[test]
+    fn test_restore_single_symbol() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+        let file = dir.path().join("lib.rs");
+        let path = file.to_str().unwrap();
+
+        let v1 = "struct A;\nimpl A {\n    fn new() -> Self { A }\n}\n\nfn other() {}\n";
+        let v2 = "struct A;\nimpl A {\n    fn new() -> Self { panic!() }\n}\n\nfn other() { let x = 1; }\n";
+        engine.save(path, v1).unwrap();
+        engine.save(path, v2).unwrap();
+        std::fs::write(&file, v2).unwrap();
+
+        let restored = engine
+            .restore_symbol(path, "new", Some("A"), None, 1)
+            .unwrap();
+
+        let expected =
+            "struct A;\nimpl A {\n    fn new() -> Self { A }\n}\n\nfn other() { let x = 1; }\n";
+        assert_eq!(restored, expected.as_bytes());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_splice_symbol_rejects_missing_and_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+
+        engine.save("a.rs", "fn f() { 1 }\n").unwrap();
+
+        assert!(matches!(
+            engine.splice_symbol("a.rs", b"fn g() {}\n", "f", None, None, 1),
+            Err(SrpError::NotFound(_))
+        ));
+        assert!(matches!(
+            engine.splice_symbol("a.rs", b"fn f() { 1 }\n", "f", Some("Nope"), None, 1),
+            Err(SrpError::NotFound(_))
+        ));
+        // Splicing into a file that is already broken elsewhere is refused.
+        assert!(matches!(
+            engine.splice_symbol("a.rs", b"fn f() { 2 }\nfn broken( {\n", "f", None, None, 1),
+            Err(SrpError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_splice_symbol_tells_kinds_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(&dir);
+
+        engine
+            .save("a.rs", "struct Foo;\nimpl Foo {\n    fn a() {}\n}\n")
+            .unwrap();
+        let current = b"struct Foo(u8);\nimpl Foo {\n    fn b() {}\n}\n";
+
+        assert!(matches!(
+            engine.splice_symbol("a.rs", current, "Foo", None, None, 1),
+            Err(SrpError::Analysis(_))
+        ));
+        let restored = engine
+            .splice_symbol("a.rs", current, "Foo", None, Some("impl"), 1)
+            .unwrap();
+        assert_eq!(
+            restored,
+            "struct Foo(u8);\nimpl Foo {\n    fn a() {}\n}\n".as_bytes()
+        );
+        let restored = engine
+            .splice_symbol("a.rs", current, "Foo", None, Some("struct"), 1)
+            .unwrap();
+        assert_eq!(
+            restored,
+            "struct Foo;\nimpl Foo {\n    fn b() {}\n}\n".as_bytes()
+        );
+    }
+}
@@ -16,6 +16,42 @@ pub enum SrpError {
 
     #[error("Analysis error: {0}")]
     Analysis(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Integrity error: {0}")]
+    Integrity(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Incompatible protocol version: {0}")]
+    VersionMismatch(String),
+}
+
+// redb splits its errors per operation; flatten them all into `Database`.
+macro_rules! impl_from_redb {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for SrpError {
+                fn from(e: $ty) -> Self {
+                    SrpError::Database(e.to_string())
+                }
+            }
+        )*
+    };
 }
 
+impl_from_redb!(
+    redb::DatabaseError,
+    redb::TransactionError,
+    redb::TableError,
+    redb::StorageError,
+    redb::CommitError
+);
+
 pub type SrpResult<T> = Result<T, SrpError>;
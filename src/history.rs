@@ -0,0 +1,137 @@
+use crate::error::SrpResult;
+use crate::models::{RecordKind, SemanticRecord};
+use crate::protocol::{HistoryEntry, HistoryParams, HistoryResult};
+use crate::search::SymbolCache;
+use crate::storage::symbols::SymbolRegistry;
+
+/// Builds the ordered timeline of one symbol in one file.
+///
+/// The symbol may be named by any name it ever had, in any scope it was
+/// moved through (`scope`, when set, must match at that point). The latest record that
+/// gave the symbol that name, or took it away, anchors the timeline; from
+/// there a backward pass walks to the record that added the symbol and a
+/// forward pass collects every later change. Each step follows the name and
+/// scope the previous one left the symbol with, so a name that was freed
+/// and later reused by another symbol is not mistaken for the same one.
+pub fn symbol_history(
+    registry: &SymbolRegistry,
+    params: &HistoryParams,
+) -> SrpResult<HistoryResult> {
+    let mut timeline = Vec::new();
+    for snapshot in registry.snapshots_for_path(&params.path)? {
+        let records = registry.records_in_snapshot(snapshot.id)?;
+        if !records.is_empty() {
+            timeline.push((snapshot, records));
+        }
+    }
+
+    let wanted = |identity: Option<Identity>| {
+        identity.is_some_and(|(name, scope)| {
+            name == params.symbol && params.scope.as_deref().is_none_or(|s| scope == Some(s))
+        })
+    };
+    // Within one snapshot, the symbol now holding the name wins over one
+    // that gave it up.
+    let anchor = timeline
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(t, (_, records))| {
+            let holder = records.iter().position(|r| wanted(after(r)));
+            holder
+                .or_else(|| records.iter().position(|r| wanted(before(r))))
+                .map(|r| (t, r))
+        });
+    let Some((anchor, first)) = anchor else {
+        return Ok(HistoryResult {
+            path: params.path.clone(),
+            symbol: params.symbol.clone(),
+            scope: params.scope.clone(),
+            versions: Vec::new(),
+        });
+    };
+
+    // (timeline index, record index), newest first
+    let mut chain = vec![(anchor, first)];
+    let mut current = before(&timeline[anchor].1[first]);
+    for t in (0..anchor).rev() {
+        let Some(identity) = current else {
+            break;
+        };
+        let records = &timeline[t].1;
+        if let Some(r) = records.iter().position(|r| after(r) == Some(identity)) {
+            chain.push((t, r));
+            current = before(&records[r]);
+        } else if records.iter().any(|r| before(r) == Some(identity)) {
+            // The name was given up here, so any earlier holder is another symbol.
+            break;
+        }
+    }
+    chain.reverse();
+
+    let mut current = after(&timeline[anchor].1[first]);
+    for (t, (_, records)) in timeline.iter().enumerate().skip(anchor + 1) {
+        let Some(identity) = current else {
+            break;
+        };
+        if let Some(r) = records.iter().position(|r| before(r) == Some(identity)) {
+            chain.push((t, r));
+            current = after(&records[r]);
+        }
+    }
+
+    let mut symbols = SymbolCache::new(registry);
+    let mut versions = Vec::new();
+    for (t, r) in chain {
+        let (snapshot, records) = &timeline[t];
+        let record = &records[r];
+        let Some(symbol) = symbols.get(record)? else {
+            continue;
+        };
+        let (name, _) = after(record).unwrap_or((&record.symbol_name, None));
+        versions.push(HistoryEntry {
+            snapshot_id: snapshot.id,
+            timestamp: snapshot.timestamp,
+            name: name.to_string(),
+            kind: record.kind.clone(),
+            structural_hash: record.structural_hash.clone(),
+            start_byte: symbol.start_byte,
+            end_byte: symbol.end_byte,
+            record: record.clone(),
+        });
+    }
+
+    Ok(HistoryResult {
+        path: params.path.clone(),
+        symbol: params.symbol.clone(),
+        scope: params.scope.clone(),
+        versions,
+    })
+}
+
+/// A symbol's name and scope at one point of its timeline.
+type Identity<'a> = (&'a str, Option<&'a str>);
+
+/// Who the symbol was before `record`; unset when it was added.
+fn before(record: &SemanticRecord) -> Option<Identity<'_>> {
+    match record.kind {
+        RecordKind::Added => None,
+        _ => Some((&record.symbol_name, record.scope.as_deref())),
+    }
+}
+
+/// Who the symbol is after `record`; unset when it was deleted.
+fn after(record: &SemanticRecord) -> Option<Identity<'_>> {
+    match record.kind {
+        RecordKind::Deleted => None,
+        RecordKind::Renamed => Some((
+            record.new_name.as_deref().unwrap_or(&record.symbol_name),
+            record.scope.as_deref(),
+        )),
+        RecordKind::Moved => Some((
+            record.new_name.as_deref().unwrap_or(&record.symbol_name),
+            record.new_scope.as_deref(),
+        )),
+        _ => Some((&record.symbol_name, record.scope.as_deref())),
+    }
+}
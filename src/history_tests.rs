@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::Engine;
+    use crate::models::RecordKind;
+    use crate::protocol::HistoryParams;
+    use crate::test_util;
+
+    const V1: &str =
+        "struct UserService;\nimpl UserService {\n    fn get_user(&self) -> u32 { 1 }\n}\n";
+    const V2: &str =
+        "struct UserService;\nimpl UserService {\n    fn fetch_user(&self) -> u32 { 1 }\n}\n";
+    const V3: &str = "struct UserService;\nimpl UserService {\n    fn fetch_user(&self) -> u32 { let id = 1; id }\n}\n";
+
+    fn engine_with_history(dir: &tempfile::TempDir) -> Engine {
+        test_util::engine_with_versions(dir, "src/user.rs", &[V1, V2, V3])
+    }
+
+    fn history_params(symbol: &str, scope: Option<&str>) -> HistoryParams {
+        HistoryParams {
+            path: "src/user.rs".to_string(),
+            symbol: symbol.to_string(),
+            scope: scope.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_history_follows_rename_from_new_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let history = engine.history(&history_params("fetch_user", None)).unwrap();
+
+        let kinds: Vec<RecordKind> = history.versions.iter().map(|v| v.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![RecordKind::Added, RecordKind::Renamed, RecordKind::Modified]
+        );
+        let names: Vec<&str> = history.versions.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["get_user", "fetch_user", "fetch_user"]);
+        let snapshots: Vec<i64> = history.versions.iter().map(|v| v.snapshot_id).collect();
+        assert_eq!(snapshots, vec![1, 2, 3]);
+        assert!(
+            history
+                .versions
+                .windows(2)
+                .all(|w| w[0].timestamp <= w[1].timestamp)
+        );
+
+        let last = &history.versions[2];
+        assert_eq!(
+            &V3[last.start_byte..last.end_byte],
+            "fn fetch_user(&self) -> u32 { let id = 1; id }"
+        );
+    }
+
+    #[test]
+    fn test_history_follows_rename_from_old_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let history = engine.history(&history_params("get_user", None)).unwrap();
+
+        assert_eq!(history.versions.len(), 3);
+        assert_eq!(history.versions[2].name, "fetch_user");
+    }
+
+    #[test]
+    fn test_history_respects_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let scoped = engine
+            .history(&history_params("fetch_user", Some("UserService")))
+            .unwrap();
+        assert_eq!(scoped.versions.len(), 3);
+
+        let other = engine
+            .history(&history_params("fetch_user", Some("OtherService")))
+            .unwrap();
+        assert!(other.versions.is_empty());
+    }
+
+    #[test]
+    fn test_history_ignores_earlier_holder_of_reused_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+        let path = "src/reuse.rs";
+        engine.save(path, "fn a() { 1 }\nfn keep() {}\n").unwrap();
+        engine.save(path, "fn b() { 1 }\nfn keep() {}\n").unwrap();
+        engine.save(path, "fn keep() {}\n").unwrap();
+        engine
+            .save(path, "fn keep() {}\nfn b() { let x = 2; x }\n")
+            .unwrap();
+
+        let steps = |symbol: &str| -> Vec<(RecordKind, String)> {
+            let params = HistoryParams {
+                path: path.to_string(),
+                symbol: symbol.to_string(),
+                scope: None,
+            };
+            let history = engine.history(&params).unwrap();
+            history
+                .versions
+                .into_iter()
+                .map(|v| (v.kind, v.name))
+                .collect()
+        };
+
+        assert_eq!(steps("b"), vec![(RecordKind::Added, "b".to_string())]);
+        assert_eq!(
+            steps("a"),
+            vec![
+                (RecordKind::Added, "a".to_string()),
+                (RecordKind::Renamed, "b".to_string()),
+                (RecordKind::Deleted, "b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_follows_rename_across_impl_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+        let path = "src/moved.rs";
+        let body = "(&self) -> u32 { let v = 1; v + 2 }";
+        engine
+            .save(
+                path,
+                format!("struct A;\nstruct B;\nimpl A {{\n    fn foo{body}\n}}\nimpl B {{}}\n"),
+            )
+            .unwrap();
+        engine
+            .save(
+                path,
+                format!("struct A;\nstruct B;\nimpl A {{}}\nimpl B {{\n    fn bar{body}\n}}\n"),
+            )
+            .unwrap();
+        engine
+            .save(
+                path,
+                "struct A;\nstruct B;\nimpl A {}\nimpl B {\n    fn bar(&self) -> u32 { 3 }\n}\n",
+            )
+            .unwrap();
+
+        let steps = |symbol: &str, scope: Option<&str>| -> Vec<(RecordKind, String)> {
+            let params = HistoryParams {
+                path: path.to_string(),
+                symbol: symbol.to_string(),
+                scope: scope.map(str::to_string),
+            };
+            let history = engine.history(&params).unwrap();
+            history
+                .versions
+                .into_iter()
+                .map(|v| (v.kind, v.name))
+                .collect()
+        };
+
+        let expected = vec![
+            (RecordKind::Added, "foo".to_string()),
+            (RecordKind::Moved, "bar".to_string()),
+            (RecordKind::Modified, "bar".to_string()),
+        ];
+        assert_eq!(steps("foo", None), expected);
+        assert_eq!(steps("bar", None), expected);
+        assert_eq!(steps("foo", Some("A")), expected);
+        assert_eq!(steps("bar", Some("B")), expected);
+        assert!(steps("bar", Some("A")).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::callgraph::{CallGraph, module_of};
+use crate::models::{RecordKind, SemanticRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImpactOptions {
+    /// How many calls away from a change to follow; unlimited when unset
+    pub max_depth: Option<usize>,
+}
+
+/// A symbol affected by the changes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactedSymbol {
+    /// Index into `CallGraph::nodes`
+    pub node: usize,
+    pub path: String,
+    pub name: String,
+    pub scope: Option<String>,
+    /// Calls between it and a changed symbol; 0 for the changed symbols
+    pub distance: usize,
+    /// The symbol it calls on its way to a change; unset for changed symbols
+    /// and for callers of a name that is gone
+    pub via: Option<usize>,
+}
+
+/// A file holding impacted symbols, at the distance of the closest one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactedFile {
+    pub path: String,
+    pub distance: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Impact {
+    /// By distance, then node order
+    pub symbols: Vec<ImpactedSymbol>,
+    /// By distance, then path
+    pub files: Vec<ImpactedFile>,
+    /// Set when `max_depth` stopped the walk before every caller was reached
+    pub truncated: bool,
+}
+
+/// What `records` affect: the changed symbols, their callers, their
+/// callers' callers and so on, walking `graph` backwards breadth-first.
+///
+/// `graph` should describe the code after the changes. Records are found in
+/// it by path (when set), scope and name, renamed and moved symbols under
+/// their new name. Callers still using the old name of a renamed, moved or
+/// deleted symbol no longer resolve to it; their unresolved calls by that
+/// name put them at distance 1 when made from the symbol's file, or through
+/// its module or type (`util::tax()`, `Cart::total()`, an imported `tax()`).
+/// Added and reordered symbols impact nothing.
+pub fn impact(graph: &CallGraph, records: &[SemanticRecord], options: &ImpactOptions) -> Impact {
+    let mut callers: Vec<Vec<usize>> = vec![Vec::new(); graph.nodes.len()];
+    for edge in &graph.edges {
+        callers[edge.callee].push(edge.caller);
+    }
+
+    // node -> (distance, via)
+    let mut reached: BTreeMap<usize, (usize, Option<usize>)> = BTreeMap::new();
+    let mut seed = |node: usize, distance: usize| {
+        let closest = reached
+            .get(&node)
+            .map_or(distance, |&(d, _)| d.min(distance));
+        reached.insert(node, (closest, None));
+    };
+
+    for record in records {
+        let (name, scope, path) = match record.kind {
+            RecordKind::Modified => (&record.symbol_name, &record.scope, &record.path),
+            RecordKind::Renamed => (
+                record.new_name.as_ref().unwrap_or(&record.symbol_name),
+                &record.scope,
+                &record.path,
+            ),
+            RecordKind::Moved => (
+                record.new_name.as_ref().unwrap_or(&record.symbol_name),
+                &record.new_scope,
+                if record.new_path.is_some() {
+                    &record.new_path
+                } else {
+                    &record.path
+                },
+            ),
+            RecordKind::Deleted => (&record.symbol_name, &record.scope, &record.path),
+            RecordKind::Added | RecordKind::Reordered => continue,
+        };
+        if record.kind != RecordKind::Deleted {
+            for n in 0..graph.nodes.len() {
+                let node = &graph.nodes[n];
+                if node.name == *name
+                    && node.scope == *scope
+                    && path.as_ref().is_none_or(|p| *p == node.path)
+                {
+                    seed(n, 0);
+                }
+            }
+        }
+        if record.kind != RecordKind::Modified {
+            let module = record.path.as_deref().map(module_of);
+            let owner = record.scope.as_deref().and_then(|s| s.rsplit("::").next());
+            for call in &graph.unresolved {
+                let reaches = record.path.as_ref().is_none_or(|p| {
+                    graph.nodes[call.caller].path == *p
+                        || call
+                            .module
+                            .as_deref()
+                            .is_some_and(|m| module.as_deref() == Some(m) || owner == Some(m))
+                });
+                if call.name == record.symbol_name && reaches {
+                    seed(call.caller, 1);
+                }
+            }
+        }
+    }
+
+    // Closest seeds first keeps the walk breadth-first.
+    let mut seeds: Vec<usize> = reached.keys().copied().collect();
+    seeds.sort_by_key(|n| (reached[n].0, *n));
+    let mut queue = VecDeque::from(seeds);
+    let mut truncated = false;
+    while let Some(node) = queue.pop_front() {
+        let distance = reached[&node].0;
+        for &caller in &callers[node] {
+            if reached.contains_key(&caller) {
+                continue;
+            }
+            if options.max_depth.is_some_and(|max| distance >= max) {
+                truncated = true;
+                continue;
+            }
+            reached.insert(caller, (distance + 1, Some(node)));
+            queue.push_back(caller);
+        }
+    }
+
+    let within = |distance: usize| options.max_depth.is_none_or(|max| distance <= max);
+    truncated |= reached.values().any(|&(distance, _)| !within(distance));
+    let mut symbols: Vec<ImpactedSymbol> = reached
+        .into_iter()
+        .filter(|&(_, (distance, _))| within(distance))
+        .map(|(n, (distance, via))| ImpactedSymbol {
+            node: n,
+            path: graph.nodes[n].path.clone(),
+            name: graph.nodes[n].name.clone(),
+            scope: graph.nodes[n].scope.clone(),
+            distance,
+            via,
+        })
+        .collect();
+    symbols.sort_by_key(|s| (s.distance, s.node));
+
+    let mut closest: BTreeMap<&str, usize> = BTreeMap::new();
+    for symbol in &symbols {
+        closest.entry(&symbol.path).or_insert(symbol.distance);
+    }
+    let mut files: Vec<ImpactedFile> = closest
+        .into_iter()
+        .map(|(path, distance)| ImpactedFile {
+            path: path.to_string(),
+            distance,
+        })
+        .collect();
+    files.sort_by(|a, b| (a.distance, &a.path).cmp(&(b.distance, &b.path)));
+
+    Impact {
+        symbols,
+        files,
+        truncated,
+    }
+}
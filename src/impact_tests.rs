@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests {
+    use crate::callgraph::CallGraph;
+    use crate::impact::{Impact, ImpactOptions, impact};
+    use crate::models::SemanticRecord;
+    use crate::project::{ProjectTree, diff_trees};
+    use crate::semantic::diff::DiffOptions;
+    use crate::test_util;
+    use bytes::Bytes;
+
+    const UTIL: &str = "pub fn tax(amount: u32) -> u32 {\n    amount / 10\n}\n\npub fn label(amount: u32) -> String {\n    format!(\"{} EUR\", amount)\n}\n";
+    const CART: &str =
+        "use crate::util::tax;\n\nfn total(amount: u32) -> u32 {\n    amount + tax(amount)\n}\n";
+    const CHECKOUT: &str = "fn checkout() -> u32 {\n    total(100)\n}\n";
+    const MAIN: &str = "use crate::util::label;\n\nfn main() {\n    checkout();\n}\n\nfn show() -> String {\n    label(5)\n}\n";
+    const OTHER: &str = "fn unrelated() -> u32 {\n    7\n}\n";
+
+    fn tree(util: &str) -> ProjectTree {
+        let mut tree = ProjectTree::default();
+        for (path, content) in [
+            ("src/util.rs", util),
+            ("src/cart.rs", CART),
+            ("src/checkout.rs", CHECKOUT),
+            ("src/main.rs", MAIN),
+            ("src/other.rs", OTHER),
+        ] {
+            tree.files
+                .insert(path.to_string(), Bytes::from(content.to_string()));
+        }
+        tree
+    }
+
+    /// Symbol names and distances impacted by changing `util.rs` to `util`.
+    fn run(util: &str, options: &ImpactOptions) -> (Vec<(String, usize)>, Impact) {
+        let (old, new) = (tree(UTIL), tree(util));
+        let diff = diff_trees(&old, &new, Some(1), 2, &DiffOptions::default()).unwrap();
+        let records: Vec<_> = diff.files.into_iter().flat_map(|f| f.records).collect();
+        let graph = CallGraph::from_tree(&new).unwrap();
+
+        let impact = impact(&graph, &records, options);
+        let symbols = impact
+            .symbols
+            .iter()
+            .map(|s| (s.name.clone(), s.distance))
+            .collect();
+        (symbols, impact)
+    }
+
+    #[test]
+    fn test_impact_walks_callers_transitively() {
+        let (symbols, impact) = run(
+            &UTIL.replace("amount / 10", "amount / 5"),
+            &ImpactOptions::default(),
+        );
+
+        assert_eq!(
+            symbols,
+            vec![
+                ("tax".to_string(), 0),
+                ("total".to_string(), 1),
+                ("checkout".to_string(), 2),
+                ("main".to_string(), 3),
+            ]
+        );
+        let files: Vec<(&str, usize)> = impact
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.distance))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("src/util.rs", 0),
+                ("src/cart.rs", 1),
+                ("src/checkout.rs", 2),
+                ("src/main.rs", 3),
+            ]
+        );
+        assert_eq!(impact.symbols[1].via, Some(impact.symbols[0].node));
+        assert!(!impact.truncated);
+    }
+
+    #[test]
+    fn test_impact_stops_at_max_depth() {
+        let (symbols, impact) = run(
+            &UTIL.replace("amount / 10", "amount / 5"),
+            &ImpactOptions { max_depth: Some(1) },
+        );
+
+        assert_eq!(
+            symbols,
+            vec![("tax".to_string(), 0), ("total".to_string(), 1)]
+        );
+        assert!(impact.truncated);
+    }
+
+    #[test]
+    fn test_impact_reaches_callers_of_renamed_symbol() {
+        let (symbols, _) = run(
+            &UTIL.replace("fn label(", "fn caption("),
+            &ImpactOptions::default(),
+        );
+
+        assert_eq!(
+            symbols,
+            vec![("caption".to_string(), 0), ("show".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_engine_impact_of_saved_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+        for (path, content) in tree(UTIL).files {
+            engine.save(&path, content).unwrap();
+        }
+
+        let records = engine
+            .save("src/util.rs", UTIL.replace("amount / 10", "amount / 5"))
+            .unwrap();
+        let impact = engine
+            .impact(&records, &ImpactOptions { max_depth: Some(2) })
+            .unwrap();
+
+        let names: Vec<&str> = impact.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["tax", "total", "checkout"]);
+        assert!(impact.truncated);
+        assert!(
+            records
+                .iter()
+                .all(|r| r.path.as_deref() == Some("src/util.rs"))
+        );
+    }
+
+    #[test]
+    fn test_engine_impact_keeps_same_names_in_other_files_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+        let a = "pub fn run() -> u32 {\n    1\n}\n\nfn call_a() -> u32 {\n    run()\n}\n\npub fn stop() {}\n\nfn halt_a() {\n    stop()\n}\n";
+        let b = "pub fn run() -> u32 {\n    2\n}\n\nfn call_b() -> u32 {\n    run()\n}\n\nfn halt_b() {\n    stop()\n}\n";
+        let c = "use crate::a::stop;\n\nfn halt_c() {\n    stop()\n}\n";
+        engine.save("src/a.rs", a).unwrap();
+        engine.save("src/b.rs", b).unwrap();
+        engine.save("src/c.rs", c).unwrap();
+
+        let impacted = |records: &[SemanticRecord]| -> Vec<(String, String, usize)> {
+            let impact = engine.impact(records, &ImpactOptions::default()).unwrap();
+            impact
+                .symbols
+                .into_iter()
+                .map(|s| (s.path, s.name, s.distance))
+                .collect()
+        };
+        let entry =
+            |path: &str, name: &str, distance| (path.to_string(), name.to_string(), distance);
+
+        let records = engine.save("src/a.rs", a.replace("1\n", "10\n")).unwrap();
+        assert_eq!(
+            impacted(&records),
+            vec![entry("src/a.rs", "run", 0), entry("src/a.rs", "call_a", 1)]
+        );
+
+        // Calls to the old name count from its own file and from files
+        // importing it, not from a file that merely uses the same name.
+        let records = engine
+            .save(
+                "src/a.rs",
+                a.replace("1\n", "10\n")
+                    .replace("pub fn stop(", "pub fn end("),
+            )
+            .unwrap();
+        assert_eq!(
+            impacted(&records),
+            vec![
+                entry("src/a.rs", "end", 0),
+                entry("src/a.rs", "halt_a", 1),
+                entry("src/c.rs", "halt_c", 1),
+            ]
+        );
+    }
+}
@@ -1,9 +1,37 @@
+pub mod callgraph;
+#[cfg(test)]
+mod callgraph_tests;
+pub mod engine;
+#[cfg(test)]
+mod engine_tests;
 pub mod error;
+pub mod history;
+#[cfg(test)]
+mod history_tests;
+pub mod impact;
+#[cfg(test)]
+mod impact_tests;
 pub mod interner;
 pub mod models;
+pub mod patch;
+#[cfg(test)]
+mod patch_tests;
+pub mod project;
+#[cfg(test)]
+mod project_tests;
 pub mod protocol;
+pub mod search;
+#[cfg(test)]
+mod search_tests;
 pub mod semantic;
+pub mod server;
+#[cfg(test)]
+mod server_tests;
+pub mod storage;
+#[cfg(test)]
+mod test_util;
 
+pub use engine::{Config, Engine};
 pub use error::{SrpError, SrpResult};
 pub use interner::{INTERNER, intern, resolve};
 pub use models::*;
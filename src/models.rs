@@ -1,4 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SemanticSymbol {
@@ -13,23 +15,79 @@ pub struct SemanticSymbol {
     pub end_line: usize,
     pub start_byte: usize,
     pub end_byte: usize,
+    /// Id of the innermost symbol containing this one (e.g. the struct of a
+    /// field). Parsed symbols carry provisional ids, renumbered on storage
     pub parent_id: Option<i64>,
+    /// Subtree hashes from `StructuralFingerprinter::shape`, used to score
+    /// similarity between symbols whose `structural_hash` differs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shape: Vec<u64>,
+    /// Per-aspect hashes from `StructuralFingerprinter::hashes`
+    #[serde(default)]
+    pub hashes: SymbolHashes,
 }
 
+/// Hashes of one aspect each of a symbol's source, so a modification can be
+/// classified. Empty strings mean unknown and never differ from anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolHashes {
+    /// Tokens outside the body: name, parameters, return type, modifiers
+    pub signature: String,
+    /// Structural hash of the body
+    pub body: String,
+    /// Literal values, in order
+    pub literals: String,
+    /// Comments inside the symbol and doc comments above it
+    pub docs: String,
+    /// Every non-comment token, ignoring whitespace
+    pub tokens: String,
+    /// The exact source text
+    pub text: String,
+    /// Every token outside nested symbols (fields, variants, members), so a
+    /// parent can tell its own edits from its children's
+    #[serde(default)]
+    pub own: String,
+}
+
+/// A use of a name in source: a call or an import.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SymbolReference {
+    /// The called name; for `Import`, the name the import binds locally
     pub symbol_name: String,
     pub snapshot_id: i64,
     pub start_line: usize,
     pub start_byte: usize,
+    #[serde(default)]
+    pub kind: ReferenceKind,
+    /// For `Call`, the path before the name (`Config` in `Config::load()`);
+    /// for `MethodCall`, the receiver when it is a plain name (`self`); for
+    /// `Import`, the full imported path
+    #[serde(default)]
+    pub qualifier: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferenceKind {
+    /// `name()` or `path::name()`
+    #[default]
+    Call,
+    /// `receiver.name()`
+    MethodCall,
+    /// `use path::name;`
+    Import,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RecordKind {
     Added,
     Modified,
     Deleted,
     Renamed,
+    /// Moved to another file or scope; `path` / `scope` are the old location,
+    /// `new_path` / `new_scope` the new one (`new_path` is unset within a file)
+    Moved,
+    /// Same symbol, now at a different place among the symbols of its scope
+    Reordered,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,8 +98,81 @@ pub struct SemanticRecord {
     pub to_snapshot_id: i64,
     pub symbol_name: String,
     pub new_name: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// On `Moved`, the scope at the new location; `scope` is then the old one
+    #[serde(default)]
+    pub new_scope: Option<String>,
+    /// For a nested symbol, the name of the symbol containing it (in the new
+    /// version, or the old one for `Deleted`)
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// File the record applies to, when known
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Set when the symbol changed file; `path` is then the old one
+    #[serde(default)]
+    pub new_path: Option<String>,
+    /// How sure the differ is that both sides are the same symbol, from 0.0
+    /// to 1.0; set on `Renamed` and `Moved`
+    #[serde(default)]
+    pub confidence: Option<f64>,
     pub kind: RecordKind,
     pub structural_hash: String,
+    /// What changed, set on `Modified`; empty when the symbols carry no `hashes`
+    #[serde(default)]
+    pub flags: Vec<ChangeFlag>,
+    /// AST edits inside the symbol; filled in for `Modified` when both
+    /// versions of the source are available
+    #[serde(default)]
+    pub changes: Vec<NodeChange>,
+}
+
+/// Which aspects of a modified symbol differ between its two versions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeFlag {
+    SignatureChanged,
+    BodyChanged,
+    LiteralChanged,
+    DocChanged,
+    /// Nested symbols changed; their records name this one as `parent`. On
+    /// its own, nothing else in the symbol changed
+    ChildrenChanged,
+    /// Only whitespace or layout changed
+    FormatOnly,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeChangeKind {
+    Inserted,
+    Deleted,
+    /// Same node in both versions, with different text or operators
+    Updated,
+    /// Same node in both versions, under a different parent
+    Moved,
+}
+
+/// One syntax node edit between two versions of a symbol.
+///
+/// Ranges are byte offsets into the old and new file; each side is only set
+/// when the node exists there. Texts are whitespace-collapsed snippets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeChange {
+    pub kind: NodeChangeKind,
+    /// Tree-sitter node kind, e.g. `if_expression`
+    pub node_kind: String,
+    pub old_range: Option<Range<usize>>,
+    pub new_range: Option<Range<usize>>,
+    pub old_text: Option<String>,
+    pub new_text: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: i64,
+    pub path: String,
+    pub timestamp: DateTime<Utc>,
+    pub file_hash: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -0,0 +1,424 @@
+use crate::error::{SrpError, SrpResult};
+use crate::models::SemanticSymbol;
+use crate::semantic::SemanticParser;
+use crate::semantic::diff::{SemanticDiffer, canonical};
+use crate::storage::cas::CasStore;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Text of one version of a symbol.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchText {
+    Inline(String),
+    /// BLAKE3 hash of the text in the `CasStore`
+    Chunk(String),
+}
+
+/// One version of a symbol in a patch.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PatchSymbol {
+    pub name: String,
+    pub scope: Option<String>,
+    pub kind: String,
+    /// On the old side, the hash the base must have; on the new side, the
+    /// hash the symbol must have once applied
+    pub structural_hash: String,
+    /// For replacements, the symbol's own text. For additions and deletions
+    /// it spans whole lines, with indentation and the blank lines above.
+    pub text: PatchText,
+}
+
+/// A change to one symbol: replaced when both sides are set, otherwise
+/// added or deleted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PatchOp {
+    /// The symbol before the change; unset when it is added
+    pub old: Option<PatchSymbol>,
+    /// After the change; unset when it is deleted
+    pub new: Option<PatchSymbol>,
+    /// For additions and deletions, the sibling directly before the symbol
+    pub after: Option<String>,
+    /// Likewise, the sibling directly after it
+    pub before: Option<String>,
+    /// Likewise, the symbol containing it
+    pub parent: Option<String>,
+}
+
+impl PatchOp {
+    /// Replacements, then deletions, then additions.
+    fn order(&self) -> u8 {
+        match (&self.old, &self.new) {
+            (Some(_), Some(_)) => 0,
+            (Some(_), None) => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// Replayable changes to the symbols of one file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SemanticPatch {
+    /// Extension of the file, which selects the parser
+    pub extension: String,
+    /// Replacements first, then deletions, then additions in source order
+    pub ops: Vec<PatchOp>,
+}
+
+impl SemanticPatch {
+    /// Moves every inline text into `cas`, leaving chunk references.
+    pub fn store_texts(&mut self, cas: &CasStore) -> SrpResult<()> {
+        for text in self.texts_mut() {
+            if let PatchText::Inline(inline) = text {
+                *text = PatchText::Chunk(cas.put(inline.as_bytes())?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces every chunk reference with its text from `cas`.
+    pub fn load_texts(&mut self, cas: &CasStore) -> SrpResult<()> {
+        for text in self.texts_mut() {
+            if let PatchText::Chunk(hash) = text {
+                let bytes = cas.get(hash)?;
+                let inline = String::from_utf8(bytes.to_vec()).map_err(|_| {
+                    SrpError::Integrity(format!("Patch text {} is not UTF-8", hash))
+                })?;
+                *text = PatchText::Inline(inline);
+            }
+        }
+        Ok(())
+    }
+
+    fn texts_mut(&mut self) -> impl Iterator<Item = &mut PatchText> {
+        self.ops
+            .iter_mut()
+            .flat_map(|op| [op.old.as_mut(), op.new.as_mut()])
+            .flatten()
+            .map(|symbol| &mut symbol.text)
+    }
+}
+
+/// Builds the patch turning `old` into `new`, with inline texts.
+///
+/// Symbols are paired as by `SemanticDiffer::compare`, renames included. A
+/// changed symbol is replaced whole, unless only its nested symbols changed:
+/// those then get ops of their own. A symbol that changed parent is deleted
+/// and added again. Pure reorderings are not carried.
+pub fn create(extension: &str, old: &[u8], new: &[u8]) -> SrpResult<SemanticPatch> {
+    let mut parser = SemanticParser::new()?;
+    let prev = parse(&mut parser, extension, old)?;
+    let curr = parse(&mut parser, extension, new)?;
+    let counterparts = SemanticDiffer::counterparts(&prev, &curr);
+    let prev_parents = SemanticDiffer::parents(&prev);
+    let curr_parents = SemanticDiffer::parents(&curr);
+
+    // `done`: the symbol is covered, by an op or by being unchanged.
+    let mut prev_done = vec![false; prev.len()];
+    let mut curr_done = vec![false; curr.len()];
+    // Paired and in the same parent on both sides
+    let mut kept = vec![false; curr.len()];
+    let mut ops = Vec::new();
+
+    for p in 0..prev.len() {
+        if prev_parents[p].is_some_and(|pp| prev_done[pp]) {
+            prev_done[p] = true;
+            continue;
+        }
+        let same_place = |c: usize| match (prev_parents[p], curr_parents[c]) {
+            (None, None) => true,
+            (Some(pp), Some(cp)) => counterparts[pp] == Some(cp),
+            _ => false,
+        };
+        let Some(c) = counterparts[p].filter(|&c| same_place(c)) else {
+            ops.push(placed(&prev, &prev_parents, old, p, true)?);
+            prev_done[p] = true;
+            continue;
+        };
+        kept[c] = true;
+
+        let (before, after) = (&prev[p], &curr[c]);
+        if old[before.start_byte..before.end_byte] == new[after.start_byte..after.end_byte] {
+            prev_done[p] = true;
+            curr_done[c] = true;
+        } else if before.name != after.name
+            || shell(old, &prev, &prev_parents, p) != shell(new, &curr, &curr_parents, c)
+        {
+            ops.push(PatchOp {
+                old: Some(patch_symbol(
+                    before,
+                    &old[before.start_byte..before.end_byte],
+                )?),
+                new: Some(patch_symbol(after, &new[after.start_byte..after.end_byte])?),
+                after: None,
+                before: None,
+                parent: None,
+            });
+            prev_done[p] = true;
+            curr_done[c] = true;
+        }
+    }
+
+    for c in 0..curr.len() {
+        if curr_parents[c].is_some_and(|cp| curr_done[cp]) {
+            curr_done[c] = true;
+        } else if !kept[c] {
+            ops.push(placed(&curr, &curr_parents, new, c, false)?);
+            curr_done[c] = true;
+        }
+    }
+
+    ops.sort_by_key(PatchOp::order);
+    Ok(SemanticPatch {
+        extension: extension.to_string(),
+        ops,
+    })
+}
+
+/// Applies `patch` to `base`.
+///
+/// Symbols are looked up by name, scope and kind, so the patch applies
+/// wherever they moved to. Every replaced or deleted symbol must be found
+/// with its old structural hash, every added one must not exist yet, and
+/// each op must leave its new symbol in place; otherwise the patch is
+/// rejected with `SrpError::Integrity`. Texts must be inline (see
+/// `SemanticPatch::load_texts`). The result must parse cleanly.
+pub fn apply(base: &[u8], patch: &SemanticPatch) -> SrpResult<Bytes> {
+    let extension = patch.extension.as_str();
+    let mut parser = SemanticParser::new()?;
+    let mut content = base.to_vec();
+    let mut symbols = parse(&mut parser, extension, &content)?;
+
+    for op in &patch.ops {
+        let (range, text) = match (&op.old, &op.new) {
+            (Some(old), new) => {
+                let symbol = find(&symbols, old).ok_or_else(|| {
+                    precondition(old, "is missing or differs from the patch's base")
+                })?;
+                let range = symbol.start_byte..symbol.end_byte;
+                match new {
+                    Some(new) => (range, inline(new)?),
+                    None => (extent(&content, range), ""),
+                }
+            }
+            (None, Some(new)) => {
+                if find(&symbols, new).is_some() {
+                    return Err(precondition(new, "is already present"));
+                }
+                let mut at = insertion_point(&content, &symbols, op, new)?;
+                if at == content.len() && content.last().is_some_and(|&b| b != b'\n') {
+                    content.push(b'\n');
+                    at += 1;
+                }
+                (at..at, inline(new)?)
+            }
+            (None, None) => continue,
+        };
+
+        content.splice(range, text.bytes());
+        symbols = parse(&mut parser, extension, &content)?;
+        if let Some(new) = &op.new {
+            if find(&symbols, new).is_none() {
+                return Err(precondition(new, "does not come out as in the patch"));
+            }
+        }
+    }
+
+    if parser.has_syntax_errors(&content, extension)? {
+        return Err(SrpError::Parse(
+            "Applying the patch leaves syntax errors".to_string(),
+        ));
+    }
+    Ok(Bytes::from(content))
+}
+
+/// The patch undoing `patch`: every op with its sides swapped.
+pub fn invert(patch: &SemanticPatch) -> SemanticPatch {
+    let mut ops: Vec<PatchOp> = patch
+        .ops
+        .iter()
+        .map(|op| PatchOp {
+            old: op.new.clone(),
+            new: op.old.clone(),
+            ..op.clone()
+        })
+        .collect();
+    ops.sort_by_key(PatchOp::order);
+    SemanticPatch {
+        extension: patch.extension.clone(),
+        ops,
+    }
+}
+
+fn parse(
+    parser: &mut SemanticParser,
+    extension: &str,
+    content: &[u8],
+) -> SrpResult<Vec<SemanticSymbol>> {
+    let symbols = parser.parse_symbols(&Bytes::copy_from_slice(content), extension, 0, None)?;
+    Ok(canonical(&symbols).into_owned())
+}
+
+/// Patches carry text, so a symbol that is not valid UTF-8 is an error
+/// rather than being silently altered.
+fn patch_symbol(symbol: &SemanticSymbol, text: &[u8]) -> SrpResult<PatchSymbol> {
+    let text = String::from_utf8(text.to_vec())
+        .map_err(|_| SrpError::Analysis(format!("Symbol {} is not UTF-8", symbol.name)))?;
+    Ok(PatchSymbol {
+        name: symbol.name.clone(),
+        scope: symbol.scope.clone(),
+        kind: symbol.kind.clone(),
+        structural_hash: symbol.structural_hash.clone(),
+        text: PatchText::Inline(text),
+    })
+}
+
+/// An op adding (or deleting, with `old`) symbol `i` with its surroundings.
+fn placed(
+    symbols: &[SemanticSymbol],
+    parents: &[Option<usize>],
+    content: &[u8],
+    i: usize,
+    old: bool,
+) -> SrpResult<PatchOp> {
+    let symbol = &symbols[i];
+    let siblings: Vec<usize> = (0..symbols.len())
+        .filter(|&j| parents[j] == parents[i])
+        .collect();
+    let k = siblings
+        .iter()
+        .position(|&j| j == i)
+        .expect("a symbol is its own sibling");
+    let name = |j: usize| symbols[j].name.clone();
+
+    let text = patch_symbol(
+        symbol,
+        &content[extent(content, symbol.start_byte..symbol.end_byte)],
+    )?;
+    Ok(PatchOp {
+        old: old.then(|| text.clone()),
+        new: (!old).then_some(text),
+        after: k.checked_sub(1).map(|k| name(siblings[k])),
+        before: siblings.get(k + 1).map(|&j| name(j)),
+        parent: parents[i].map(name),
+    })
+}
+
+/// Symbol `i`'s text without the extents of its direct children.
+fn shell(
+    content: &[u8],
+    symbols: &[SemanticSymbol],
+    parents: &[Option<usize>],
+    i: usize,
+) -> Vec<u8> {
+    let symbol = &symbols[i];
+    let mut out = Vec::new();
+    let mut pos = symbol.start_byte;
+    for child in (0..symbols.len()).filter(|&j| parents[j] == Some(i)) {
+        let range = extent(content, symbols[child].start_byte..symbols[child].end_byte);
+        out.extend_from_slice(&content[pos..range.start.max(pos)]);
+        pos = pos.max(range.end);
+    }
+    out.extend_from_slice(&content[pos..symbol.end_byte.max(pos)]);
+    out
+}
+
+/// `range` widened to whole lines, with the blank lines above, when nothing
+/// else shares those lines.
+fn extent(content: &[u8], range: Range<usize>) -> Range<usize> {
+    let blank = |b: &u8| matches!(b, b' ' | b'\t' | b'\r');
+    let line_start = |at: usize| {
+        content[..at]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1)
+    };
+
+    let mut start = range.start;
+    if content[line_start(start)..start].iter().all(blank) {
+        start = line_start(start);
+        while start > 0 {
+            let above = line_start(start - 1);
+            if !content[above..start - 1].iter().all(blank) {
+                break;
+            }
+            start = above;
+        }
+    }
+
+    let mut end = range.end;
+    let line_end = content[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(content.len(), |i| end + i + 1);
+    if content[end..line_end]
+        .iter()
+        .all(|b| blank(b) || *b == b'\n')
+    {
+        end = line_end;
+    }
+    start..end
+}
+
+/// Where an added symbol goes: after its preceding sibling, before its
+/// following one, at the end of its parent, or at the end of the file.
+fn insertion_point(
+    content: &[u8],
+    symbols: &[SemanticSymbol],
+    op: &PatchOp,
+    new: &PatchSymbol,
+) -> SrpResult<usize> {
+    let find_named = |name: &str, sibling: bool| {
+        symbols
+            .iter()
+            .find(|s| s.name == name && (!sibling || s.scope == new.scope))
+            .ok_or_else(|| precondition(new, &format!("has no {} to go next to", name)))
+    };
+
+    if let Some(after) = &op.after {
+        let s = find_named(after, true)?;
+        return Ok(extent(content, s.start_byte..s.end_byte).end);
+    }
+    if let Some(before) = &op.before {
+        let s = find_named(before, true)?;
+        return Ok(extent(content, s.start_byte..s.end_byte).start);
+    }
+    if let Some(parent) = &op.parent {
+        // Start of the line holding the parent's closing delimiter
+        let s = find_named(parent, false)?;
+        let last = s.end_byte.saturating_sub(1).max(s.start_byte);
+        return Ok(content[..last]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(last, |i| i + 1)
+            .max(s.start_byte));
+    }
+    Ok(content.len())
+}
+
+fn find<'a>(symbols: &'a [SemanticSymbol], wanted: &PatchSymbol) -> Option<&'a SemanticSymbol> {
+    symbols.iter().find(|s| {
+        s.name == wanted.name
+            && s.scope == wanted.scope
+            && s.kind == wanted.kind
+            && s.structural_hash == wanted.structural_hash
+    })
+}
+
+fn inline(symbol: &PatchSymbol) -> SrpResult<&str> {
+    match &symbol.text {
+        PatchText::Inline(text) => Ok(text),
+        PatchText::Chunk(hash) => Err(SrpError::NotFound(format!(
+            "Text of {} is chunk {}; load it before applying",
+            symbol.name, hash
+        ))),
+    }
+}
+
+fn precondition(symbol: &PatchSymbol, problem: &str) -> SrpError {
+    let name = match &symbol.scope {
+        Some(scope) => format!("{}::{}", scope, symbol.name),
+        None => symbol.name.clone(),
+    };
+    SrpError::Integrity(format!("Patch does not apply: {} {}", name, problem))
+}
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::SrpError;
+    use crate::patch::{PatchText, SemanticPatch, apply, create, invert};
+    use crate::test_util;
+
+    const OLD: &str = "use std::fmt;\n\nfn parse(input: &str) -> u32 {\n    input.len() as u32\n}\n\nfn render(value: u32) -> String {\n    value.to_string()\n}\n\nfn unused() {}\n\nimpl Point {\n    fn x(&self) -> i32 {\n        self.x\n    }\n\n    fn y(&self) -> i32 {\n        self.y\n    }\n}\n";
+
+    /// `OLD` with `parse` edited, `render` renamed, `unused` deleted, a
+    /// function added and one method of `Point` edited.
+    fn new_version() -> String {
+        OLD.replace("input.len() as u32", "input.trim().len() as u32")
+            .replace("fn render(", "fn display(")
+            .replace("fn unused() {}\n\n", "")
+            .replace(
+                "impl Point {",
+                "fn added() -> bool {\n    true\n}\n\nimpl Point {",
+            )
+            .replace("self.y\n", "self.y * 2\n")
+    }
+
+    fn text(bytes: &[u8]) -> &str {
+        std::str::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_patch_reproduces_new_version() {
+        let new = new_version();
+        let patch = create("rs", OLD.as_bytes(), new.as_bytes()).unwrap();
+
+        // Only the changed method of `Point` is carried, not the impl.
+        let names: Vec<(Option<&str>, Option<&str>)> = patch
+            .ops
+            .iter()
+            .map(|op| {
+                (
+                    op.old.as_ref().map(|s| s.name.as_str()),
+                    op.new.as_ref().map(|s| s.name.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (Some("parse"), Some("parse")),
+                (Some("render"), Some("display")),
+                (Some("y"), Some("y")),
+                (Some("unused"), None),
+                (None, Some("added")),
+            ]
+        );
+
+        assert_eq!(text(&apply(OLD.as_bytes(), &patch).unwrap()), new);
+    }
+
+    #[test]
+    fn test_patch_applies_after_unrelated_code_moved() {
+        let patch = create("rs", OLD.as_bytes(), new_version().as_bytes()).unwrap();
+        // The base moved `Point` to the top and edited `x`.
+        let impl_start = OLD.find("impl Point").unwrap();
+        let base = format!(
+            "{}\n{}",
+            &OLD[impl_start..].replace("self.x\n", "self.x + 1\n"),
+            &OLD[..impl_start]
+        );
+
+        let patched = apply(base.as_bytes(), &patch).unwrap();
+
+        let patched = text(&patched);
+        assert!(
+            patched.starts_with("impl Point {\n    fn x(&self) -> i32 {\n        self.x + 1\n")
+        );
+        assert!(patched.contains("self.y * 2\n"));
+        assert!(patched.contains("input.trim().len()"));
+        assert!(patched.contains("fn display(value: u32)"));
+        assert!(!patched.contains("fn unused"));
+        assert!(patched.contains("}\n\nfn added() -> bool {\n    true\n}\n"));
+    }
+
+    #[test]
+    fn test_patch_rejects_changed_base() {
+        let patch = create("rs", OLD.as_bytes(), new_version().as_bytes()).unwrap();
+        let base = OLD.replace("input.len() as u32", "0");
+
+        let err = apply(base.as_bytes(), &patch).unwrap_err();
+
+        assert!(matches!(err, SrpError::Integrity(ref m) if m.contains("parse")));
+        // Applying twice fails too.
+        let patched = apply(OLD.as_bytes(), &patch).unwrap();
+        assert!(apply(&patched, &patch).is_err());
+    }
+
+    #[test]
+    fn test_patch_rejects_non_utf8_symbol() {
+        let new = b"fn parse() -> &'static str {\n    \"caf\xe9\"\n}\n";
+
+        let err = create("rs", b"fn parse() {}\n", new).unwrap_err();
+
+        assert!(matches!(err, SrpError::Analysis(ref m) if m.contains("parse")));
+    }
+
+    #[test]
+    fn test_inverted_patch_restores_old_version() {
+        let new = new_version();
+        let patch = create("rs", OLD.as_bytes(), new.as_bytes()).unwrap();
+
+        let undone = apply(new.as_bytes(), &invert(&patch)).unwrap();
+
+        assert_eq!(text(&undone), OLD);
+    }
+
+    #[test]
+    fn test_engine_patch_refers_to_stored_texts() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+        engine.save("src/lib.rs", OLD).unwrap();
+        engine.save("src/lib.rs", new_version()).unwrap();
+        let snapshots = engine.registry().snapshots_for_path("src/lib.rs").unwrap();
+
+        let patch = engine
+            .semantic_patch("src/lib.rs", snapshots[0].id, snapshots[1].id)
+            .unwrap();
+        assert!(patch.ops.iter().all(|op| {
+            [&op.old, &op.new]
+                .into_iter()
+                .flatten()
+                .all(|s| matches!(s.text, PatchText::Chunk(_)))
+        }));
+
+        let json = serde_json::to_string(&patch).unwrap();
+        let patch: SemanticPatch = serde_json::from_str(&json).unwrap();
+        assert!(apply(OLD.as_bytes(), &patch).is_err());
+        let patched = engine.apply_patch(OLD.as_bytes(), &patch).unwrap();
+        assert_eq!(text(&patched), new_version());
+    }
+}
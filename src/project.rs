@@ -0,0 +1,249 @@
+use crate::error::SrpResult;
+use crate::models::{RecordKind, SemanticRecord, SemanticSymbol};
+use crate::semantic::SemanticParser;
+use crate::semantic::assignment::max_weight_assignment;
+use crate::semantic::diff::{DiffOptions, FileSymbols, SemanticDiffer, SymbolKey};
+use bytes::Bytes;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Minimum share of symbols (by `SymbolKey`) a deleted and an added file
+/// must have in common to be reported as one renamed file.
+const FILE_RENAME_SIMILARITY: f64 = 0.5;
+
+/// File contents of one version of a project, keyed by `/`-separated path
+/// relative to the project root.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectTree {
+    pub files: BTreeMap<String, Bytes>,
+}
+
+impl ProjectTree {
+    /// Reads every file below `root`. Hidden entries (`.git`, `.sdp`, ...)
+    /// are skipped.
+    pub fn read_dir(root: impl AsRef<Path>) -> SrpResult<Self> {
+        let mut tree = Self::default();
+        let mut pending = vec![(root.as_ref().to_path_buf(), String::new())];
+        while let Some((dir, prefix)) = pending.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path = format!("{}{}", prefix, name);
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    pending.push((entry.path(), format!("{}/", path)));
+                } else if file_type.is_file() {
+                    tree.files
+                        .insert(path, Bytes::from(std::fs::read(entry.path())?));
+                }
+            }
+        }
+        Ok(tree)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileChangeKind {
+    Added,
+    Deleted,
+    /// Path changed; the content may have changed too
+    Renamed,
+    Modified,
+}
+
+/// One changed file and the records of the symbols in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub kind: FileChangeKind,
+    /// Path in the old tree; unset for `Added`
+    pub old_path: Option<String>,
+    /// Path in the new tree; unset for `Deleted`
+    pub new_path: Option<String>,
+    /// Records carry the file's new path (its old one for `Deleted`)
+    pub records: Vec<SemanticRecord>,
+}
+
+/// Everything that changed between two versions of a project.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProjectDiff {
+    /// Changed files, by path
+    pub files: Vec<FileDiff>,
+    /// Symbols that moved from one file to another
+    pub moves: Vec<SemanticRecord>,
+}
+
+/// Diffs two versions of a project.
+///
+/// Files present in both trees with different content are `Modified`. Of
+/// the rest, a deleted and an added file are one `Renamed` file when their
+/// content is identical or they share at least `FILE_RENAME_SIMILARITY` of
+/// their symbols; pairs are chosen like symbol renames. Files are parsed and
+/// diffed in parallel; symbols are then matched across files as in
+/// `SemanticDiffer::compare_project`, treating a renamed file as one path.
+pub fn diff_trees(
+    old: &ProjectTree,
+    new: &ProjectTree,
+    from_snapshot_id: Option<i64>,
+    to_snapshot_id: i64,
+    options: &DiffOptions,
+) -> SrpResult<ProjectDiff> {
+    // Files whose content differs from (or is missing in) the other tree.
+    fn changed(
+        files: &BTreeMap<String, Bytes>,
+        other: &BTreeMap<String, Bytes>,
+    ) -> Vec<(String, Bytes)> {
+        files
+            .iter()
+            .filter(|(path, content)| other.get(*path) != Some(*content))
+            .map(|(path, content)| (path.clone(), content.clone()))
+            .collect()
+    }
+    let old_symbols = parse_all(changed(&old.files, &new.files))?;
+    let new_symbols = parse_all(changed(&new.files, &old.files))?;
+
+    let deleted: Vec<&String> = old_symbols
+        .keys()
+        .filter(|p| !new.files.contains_key(*p))
+        .collect();
+    let added: Vec<&String> = new_symbols
+        .keys()
+        .filter(|p| !old.files.contains_key(*p))
+        .collect();
+    let weights: Vec<Vec<Option<f64>>> = deleted
+        .iter()
+        .map(|&d| {
+            added
+                .iter()
+                .map(|&a| {
+                    if old.files[d] == new.files[a] {
+                        return Some(2.0);
+                    }
+                    let score = shared_symbols(&old_symbols[d], &new_symbols[a]);
+                    (score >= FILE_RENAME_SIMILARITY).then_some(score)
+                })
+                .collect()
+        })
+        .collect();
+    // new path -> old path
+    let renames: HashMap<&String, &String> = max_weight_assignment(&weights)
+        .into_iter()
+        .map(|(d, a)| (added[a], deleted[d]))
+        .collect();
+    let renamed_from: HashMap<&String, &String> = renames.iter().map(|(n, o)| (*o, *n)).collect();
+
+    // A renamed file takes part under its new path on both sides.
+    let prev_files: Vec<FileSymbols> = old_symbols
+        .iter()
+        .map(|(path, symbols)| FileSymbols {
+            path: renamed_from.get(path).copied().unwrap_or(path).clone(),
+            symbols: symbols.clone(),
+        })
+        .collect();
+    let curr_files: Vec<FileSymbols> = new_symbols
+        .iter()
+        .map(|(path, symbols)| FileSymbols {
+            path: path.clone(),
+            symbols: symbols.clone(),
+        })
+        .collect();
+    let records = SemanticDiffer::compare_project(
+        &prev_files,
+        &curr_files,
+        from_snapshot_id,
+        to_snapshot_id,
+        options,
+    );
+
+    let mut files: BTreeMap<&String, FileDiff> = BTreeMap::new();
+    for path in old_symbols.keys().chain(new_symbols.keys()) {
+        let (key, diff) = match (old.files.contains_key(path), new.files.contains_key(path)) {
+            (true, true) => (
+                path,
+                file_diff(FileChangeKind::Modified, Some(path), Some(path)),
+            ),
+            (true, false) => match renamed_from.get(path) {
+                Some(&to) => (to, file_diff(FileChangeKind::Renamed, Some(path), Some(to))),
+                None => (path, file_diff(FileChangeKind::Deleted, Some(path), None)),
+            },
+            (false, _) if renames.contains_key(path) => continue,
+            (false, _) => (path, file_diff(FileChangeKind::Added, None, Some(path))),
+        };
+        files.entry(key).or_insert(diff);
+    }
+
+    let mut moves = Vec::new();
+    for record in records {
+        if record.kind == RecordKind::Moved && record.new_path.is_some() {
+            moves.push(record);
+            continue;
+        }
+        let file = record
+            .path
+            .as_ref()
+            .and_then(|path| files.get_mut(path))
+            .expect("every record belongs to a changed file");
+        file.records.push(record);
+    }
+
+    Ok(ProjectDiff {
+        files: files.into_values().collect(),
+        moves,
+    })
+}
+
+fn file_diff(
+    kind: FileChangeKind,
+    old_path: Option<&String>,
+    new_path: Option<&String>,
+) -> FileDiff {
+    FileDiff {
+        kind,
+        old_path: old_path.cloned(),
+        new_path: new_path.cloned(),
+        records: vec![],
+    }
+}
+
+/// Parses every file in parallel, one parser per worker.
+fn parse_all(files: Vec<(String, Bytes)>) -> SrpResult<BTreeMap<String, Vec<SemanticSymbol>>> {
+    files
+        .into_par_iter()
+        .map_init(SemanticParser::new, |parser, (path, content)| {
+            let parser = parser.as_mut().map_err(|e| {
+                crate::error::SrpError::Internal(format!("Parser setup failed: {}", e))
+            })?;
+            let extension = Path::new(&path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("");
+            let symbols = parser.parse_symbols(&content, extension, 0, None)?;
+            Ok((path, symbols))
+        })
+        .collect()
+}
+
+/// Dice coefficient of two files' symbols, compared by `SymbolKey`.
+fn shared_symbols(a: &[SemanticSymbol], b: &[SemanticSymbol]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let mut counts: HashMap<SymbolKey, usize> = HashMap::new();
+    for symbol in a {
+        *counts.entry(SymbolKey::of(symbol)).or_default() += 1;
+    }
+    let mut shared = 0;
+    for symbol in b {
+        if let Some(count) = counts.get_mut(&SymbolKey::of(symbol)) {
+            if *count > 0 {
+                *count -= 1;
+                shared += 1;
+            }
+        }
+    }
+    (2 * shared) as f64 / (a.len() + b.len()) as f64
+}
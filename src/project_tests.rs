@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::{Config, Engine};
+    use crate::models::RecordKind;
+    use crate::project::{FileChangeKind, ProjectTree, diff_trees};
+    use crate::semantic::diff::DiffOptions;
+    use bytes::Bytes;
+
+    const PRICE: &str = "fn price(items: &[Item]) -> u64 {\n    let mut total = 0;\n    for item in items {\n        total += item.cost * item.quantity;\n    }\n    total\n}\n";
+    const CONFIG: &str =
+        "struct Config {\n    port: u16,\n}\n\nfn load() -> Config {\n    Config { port: 80 }\n}\n";
+
+    fn tree(files: &[(&str, &str)]) -> ProjectTree {
+        let mut tree = ProjectTree::default();
+        for (path, content) in files {
+            tree.files
+                .insert(path.to_string(), Bytes::from(content.to_string()));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_diff_trees_groups_files() {
+        let old = tree(&[
+            ("src/cart.rs", PRICE),
+            ("src/config.rs", CONFIG),
+            ("src/old.rs", "fn unused() {}\n"),
+            ("README.md", "# Demo\n"),
+        ]);
+        let new = tree(&[
+            ("src/billing.rs", PRICE),
+            ("src/settings.rs", &CONFIG.replace("port: 80", "port: 8080")),
+            ("src/new.rs", "fn fresh() -> bool {\n    start()\n}\n"),
+            ("README.md", "# Demo\n"),
+        ]);
+
+        let diff = diff_trees(&old, &new, Some(1), 2, &DiffOptions::default()).unwrap();
+
+        let files: Vec<_> = diff
+            .files
+            .iter()
+            .map(|f| (f.kind.clone(), f.old_path.as_deref(), f.new_path.as_deref()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (
+                    FileChangeKind::Renamed,
+                    Some("src/cart.rs"),
+                    Some("src/billing.rs")
+                ),
+                (FileChangeKind::Added, None, Some("src/new.rs")),
+                (FileChangeKind::Deleted, Some("src/old.rs"), None),
+                (
+                    FileChangeKind::Renamed,
+                    Some("src/config.rs"),
+                    Some("src/settings.rs")
+                ),
+            ]
+        );
+
+        assert!(diff.files[0].records.is_empty());
+        assert_eq!(diff.files[1].records[0].kind, RecordKind::Added);
+        assert_eq!(diff.files[2].records[0].kind, RecordKind::Deleted);
+        let settings = &diff.files[3].records;
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].kind, RecordKind::Modified);
+        assert_eq!(settings[0].symbol_name, "load");
+        assert_eq!(settings[0].path.as_deref(), Some("src/settings.rs"));
+        assert!(diff.moves.is_empty());
+    }
+
+    #[test]
+    fn test_diff_trees_reports_moves_across_files() {
+        let old = tree(&[
+            ("a.rs", &format!("{}\nfn keep() {{}}\n", PRICE)),
+            ("b.rs", "fn other() {}\n"),
+        ]);
+        let new = tree(&[
+            ("a.rs", "fn keep() {}\n"),
+            ("b.rs", &format!("fn other() {{}}\n\n{}", PRICE)),
+        ]);
+
+        let diff = diff_trees(&old, &new, Some(1), 2, &DiffOptions::default()).unwrap();
+
+        assert_eq!(diff.files.len(), 2);
+        assert!(
+            diff.files
+                .iter()
+                .all(|f| f.kind == FileChangeKind::Modified)
+        );
+        assert!(diff.files.iter().all(|f| f.records.is_empty()));
+        assert_eq!(diff.moves.len(), 1);
+        assert_eq!(diff.moves[0].symbol_name, "price");
+        assert_eq!(diff.moves[0].path.as_deref(), Some("a.rs"));
+        assert_eq!(diff.moves[0].new_path.as_deref(), Some("b.rs"));
+    }
+
+    #[test]
+    fn test_read_dir_skips_hidden_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".sdp")).unwrap();
+        std::fs::write(dir.path().join("src/nested/lib.rs"), "fn a() {}").unwrap();
+        std::fs::write(dir.path().join(".sdp/state"), "x").unwrap();
+        std::fs::write(dir.path().join(".env"), "x").unwrap();
+
+        let tree = ProjectTree::read_dir(dir.path()).unwrap();
+
+        assert_eq!(
+            tree.files.keys().collect::<Vec<_>>(),
+            vec!["src/nested/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_engine_diffs_stored_project_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(Config {
+            root: dir.path().join(".sdp"),
+            project_id: Some("demo".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        engine.save("src/config.rs", CONFIG).unwrap();
+        let before = engine.project_tree(None).unwrap();
+        engine
+            .save("src/config.rs", CONFIG.replace("port: 80", "port: 8080"))
+            .unwrap();
+        engine.save("src/cart.rs", PRICE).unwrap();
+
+        let diff = engine
+            .diff_project(&before, &engine.project_tree(None).unwrap())
+            .unwrap();
+
+        assert_eq!(diff.files.len(), 2);
+        assert_eq!(diff.files[0].kind, FileChangeKind::Added);
+        assert_eq!(diff.files[1].kind, FileChangeKind::Modified);
+        assert!(
+            diff.files
+                .iter()
+                .flat_map(|f| &f.records)
+                .all(|r| r.project_id.as_deref() == Some("demo"))
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use crate::models::{RecordKind, SemanticRecord, SemanticSymbol, SymbolReference};
+use crate::semantic::diff::DiffOptions;
+use crate::semantic::refactor::Refactoring;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::models::{SemanticSymbol, SemanticRecord, SymbolReference};
 
 /// SRP Protocol Version
 pub const SRP_VERSION: &str = "0.1.0";
@@ -9,6 +12,7 @@ pub struct SrpRequest {
     pub jsonrpc: String,
     pub id: Option<u64>,
     pub method: String,
+    #[serde(default)]
     pub params: serde_json::Value,
 }
 
@@ -16,24 +20,117 @@ pub struct SrpRequest {
 pub struct SrpResponse {
     pub jsonrpc: String,
     pub id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<SrpRpcError>,
 }
 
+impl SrpResponse {
+    pub fn success(id: Option<u64>, result: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Option<u64>, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(SrpRpcError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SrpRpcError {
     pub code: i32,
     pub message: String,
 }
 
+/// Standard JSON-RPC 2.0 error codes
+pub mod error_codes {
+    /// Invalid JSON was received
+    pub const PARSE_ERROR: i32 = -32700;
+    /// The JSON sent is not a valid request object
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// The method does not exist or is not available
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// Invalid method parameters
+    pub const INVALID_PARAMS: i32 = -32602;
+    /// Internal error while handling the request
+    pub const INTERNAL_ERROR: i32 = -32603;
+    /// Client and server protocol versions are not compatible
+    pub const VERSION_MISMATCH: i32 = -32001;
+    /// The requested snapshot, file or symbol does not exist
+    pub const NOT_FOUND: i32 = -32002;
+}
+
 /// Core methods of the Semantic Registry Protocol
 pub mod methods {
+    /// Exchange protocol versions and capabilities
+    pub const INITIALIZE: &str = "srp/initialize";
     /// Analyze a file and return semantic symbols
     pub const ANALYZE: &str = "srp/analyze";
     /// Compare two sets of symbols and return records
     pub const DIFF: &str = "srp/diff";
     /// Get the semantic history of a symbol
     pub const GET_HISTORY: &str = "srp/history";
+    /// Rebuild a file as it was at a given snapshot
+    pub const RESTORE: &str = "srp/restore";
+    /// Search stored symbols and records
+    pub const SEARCH: &str = "srp/search";
+}
+
+/// Optional features a server may offer during `srp/initialize`
+pub mod features {
+    /// `srp/history` is available
+    pub const HISTORY: &str = "history";
+    /// `srp/search` is available
+    pub const SEARCH: &str = "search";
+}
+
+/// Returns true if a peer speaking `other` can talk to this `SRP_VERSION`.
+/// Versions follow semver: the major must match, and while the major is 0 the minor must match too.
+pub fn is_compatible_version(other: &str) -> bool {
+    fn major_minor(v: &str) -> Option<(u64, u64)> {
+        let mut parts = v.trim().split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+
+    match (major_minor(SRP_VERSION), major_minor(other)) {
+        (Some((0, ours)), Some((0, theirs))) => ours == theirs,
+        (Some((ours, _)), Some((theirs, _))) => ours == theirs,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitializeParams {
+    pub protocol_version: String,
+    /// Optional features the client would like to use (see `features`)
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitializeResult {
+    pub protocol_version: String,
+    pub server_version: String,
+    /// File extensions the server can analyze
+    pub languages: Vec<String>,
+    /// Optional features enabled for the session: those the client asked
+    /// for that the server offers (see `features`)
+    pub features: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,9 +149,167 @@ pub struct AnalyzeResult {
 pub struct DiffParams {
     pub base_symbols: Vec<SemanticSymbol>,
     pub target_symbols: Vec<SemanticSymbol>,
+    #[serde(default)]
+    pub options: DiffOptions,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiffResult {
     pub records: Vec<SemanticRecord>,
+    /// Extract / inline / split / merge groups over `records`
+    #[serde(default)]
+    pub refactorings: Vec<Refactoring>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryParams {
+    pub path: String,
+    /// Any name the symbol had; renames and moves are followed in both directions
+    pub symbol: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// One change in a symbol's timeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub snapshot_id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// Name of the symbol after this change (before it, for deletions)
+    pub name: String,
+    pub kind: RecordKind,
+    pub structural_hash: String,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub record: SemanticRecord,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryResult {
+    pub path: String,
+    pub symbol: String,
+    pub scope: Option<String>,
+    /// Oldest first
+    pub versions: Vec<HistoryEntry>,
+}
+
+/// Selects a snapshot by id, or the latest one taken at or before `timestamp`.
+/// With neither set, the latest snapshot of `path` is used.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreParams {
+    pub path: String,
+    #[serde(default)]
+    pub snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Leave the working file untouched and return the result instead
+    #[serde(default)]
+    pub dry_run: bool,
+    /// What a dry run returns
+    #[serde(default)]
+    pub format: RestoreFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreFormat {
+    /// The full restored content
+    #[default]
+    Content,
+    /// A unified diff from the current working file to the restored content
+    Diff,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreResult {
+    pub path: String,
+    pub snapshot_id: i64,
+    /// True when the working file was overwritten
+    pub written: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// How `SearchParams::name` is compared against symbol names.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameMatch {
+    #[default]
+    Exact,
+    Prefix,
+    /// Case-insensitive subsequence, e.g. `usvc` matches `UserService`
+    Fuzzy,
+}
+
+/// Every filter is optional; a hit must satisfy all filters that are set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchParams {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub name_match: NameMatch,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub structural_hash: Option<String>,
+    #[serde(default)]
+    pub record_kind: Option<RecordKind>,
+    /// Only records from snapshots taken at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only records from snapshots taken at or before this time
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    50
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            name: None,
+            name_match: NameMatch::default(),
+            kind: None,
+            scope: None,
+            path: None,
+            structural_hash: None,
+            record_kind: None,
+            since: None,
+            until: None,
+            offset: 0,
+            limit: default_search_limit(),
+        }
+    }
+}
+
+/// One record together with the symbol it touched and where it happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub path: String,
+    pub snapshot_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub record: SemanticRecord,
+    pub symbol: SemanticSymbol,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// Newest first
+    pub hits: Vec<SearchHit>,
+    /// Number of hits across all pages
+    pub total: usize,
+    /// Offset of the next page, if there is one
+    pub next_offset: Option<usize>,
 }
@@ -0,0 +1,187 @@
+use crate::error::SrpResult;
+use crate::models::{RecordKind, SemanticRecord, SemanticSymbol, Snapshot};
+use crate::protocol::{NameMatch, SearchHit, SearchParams, SearchResult};
+use crate::storage::symbols::SymbolRegistry;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+/// Upper bound on `SearchParams::limit`, to keep single responses small.
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+
+/// Searches every stored record, joined with the symbol it touched.
+pub fn search(registry: &SymbolRegistry, params: &SearchParams) -> SrpResult<SearchResult> {
+    let mut snapshots: HashMap<i64, Option<Snapshot>> = HashMap::new();
+    let mut symbols = SymbolCache::new(registry);
+    let mut hits = Vec::new();
+
+    for record in candidates(registry, params)?.into_iter().rev() {
+        if params
+            .record_kind
+            .as_ref()
+            .is_some_and(|kind| *kind != record.kind)
+        {
+            continue;
+        }
+
+        if let Entry::Vacant(e) = snapshots.entry(record.to_snapshot_id) {
+            e.insert(registry.get_snapshot(record.to_snapshot_id)?);
+        }
+        let Some(snapshot) = snapshots[&record.to_snapshot_id].as_ref() else {
+            continue;
+        };
+        if !matches_snapshot(params, snapshot) {
+            continue;
+        }
+
+        let Some(symbol) = symbols.get(&record)? else {
+            continue;
+        };
+
+        if matches_symbol(params, &record, &symbol) {
+            hits.push(SearchHit {
+                path: snapshot.path.clone(),
+                snapshot_id: snapshot.id,
+                timestamp: snapshot.timestamp,
+                symbol,
+                record,
+            });
+        }
+    }
+
+    let total = hits.len();
+    let limit = params.limit.min(MAX_SEARCH_LIMIT);
+    let hits: Vec<SearchHit> = hits.into_iter().skip(params.offset).take(limit).collect();
+    let end = params.offset + hits.len();
+
+    Ok(SearchResult {
+        hits,
+        total,
+        next_offset: (end < total).then_some(end),
+    })
+}
+
+/// Records that may match `params`, oldest first. Uses the path and symbol
+/// indexes when the filters allow it, so only a scan without `path` reads
+/// every stored record.
+fn candidates(registry: &SymbolRegistry, params: &SearchParams) -> SrpResult<Vec<SemanticRecord>> {
+    let Some(path) = params.path.as_deref() else {
+        return registry.records();
+    };
+
+    let mut records = match params.name.as_deref() {
+        Some(name) if params.name_match == NameMatch::Exact => {
+            registry.records_for_symbol(path, name)?
+        }
+        _ => {
+            let mut records = Vec::new();
+            for snapshot in registry.snapshots_for_path(path)? {
+                records.extend(registry.records_in_snapshot(snapshot.id)?);
+            }
+            records
+        }
+    };
+    records.sort_by_key(|r| r.id);
+    Ok(records)
+}
+
+/// Returns true if `name` matches `pattern` under `mode`.
+pub fn name_matches(pattern: &str, name: &str, mode: NameMatch) -> bool {
+    match mode {
+        NameMatch::Exact => name == pattern,
+        NameMatch::Prefix => name.starts_with(pattern),
+        NameMatch::Fuzzy => {
+            let mut chars = name.chars().flat_map(char::to_lowercase);
+            pattern
+                .chars()
+                .flat_map(char::to_lowercase)
+                .all(|p| chars.any(|c| c == p))
+        }
+    }
+}
+
+/// Lazily loaded symbols per snapshot, for joining records with the symbol they touched.
+///
+/// Added, Modified and Renamed records are joined with the symbol as it exists
+/// after the change; Deleted records with the symbol as it was before.
+pub(crate) struct SymbolCache<'a> {
+    registry: &'a SymbolRegistry,
+    by_snapshot: HashMap<i64, Vec<SemanticSymbol>>,
+}
+
+impl<'a> SymbolCache<'a> {
+    pub(crate) fn new(registry: &'a SymbolRegistry) -> Self {
+        Self {
+            registry,
+            by_snapshot: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, record: &SemanticRecord) -> SrpResult<Option<SemanticSymbol>> {
+        let snapshot_id = match record.kind {
+            RecordKind::Deleted => match record.from_snapshot_id {
+                Some(id) => id,
+                None => return Ok(None),
+            },
+            _ => record.to_snapshot_id,
+        };
+
+        let symbols = match self.by_snapshot.entry(snapshot_id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(self.registry.symbols_in_snapshot(snapshot_id)?),
+        };
+        Ok(find_symbol(symbols, record).cloned())
+    }
+}
+
+fn find_symbol<'a>(
+    symbols: &'a [SemanticSymbol],
+    record: &SemanticRecord,
+) -> Option<&'a SemanticSymbol> {
+    let (name, scope) = match record.kind {
+        RecordKind::Deleted => (&record.symbol_name, &record.scope),
+        RecordKind::Moved => (
+            record.new_name.as_ref().unwrap_or(&record.symbol_name),
+            &record.new_scope,
+        ),
+        _ => (
+            record.new_name.as_ref().unwrap_or(&record.symbol_name),
+            &record.scope,
+        ),
+    };
+
+    // Prefer the exact version the record describes when names are duplicated.
+    let candidates: Vec<&SemanticSymbol> = symbols
+        .iter()
+        .filter(|s| s.name == *name && s.scope == *scope)
+        .collect();
+    candidates
+        .iter()
+        .find(|s| s.structural_hash == record.structural_hash)
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+fn matches_snapshot(params: &SearchParams, snapshot: &Snapshot) -> bool {
+    params.path.as_ref().is_none_or(|p| *p == snapshot.path)
+        && params.since.is_none_or(|t| snapshot.timestamp >= t)
+        && params.until.is_none_or(|t| snapshot.timestamp <= t)
+}
+
+fn matches_symbol(params: &SearchParams, record: &SemanticRecord, symbol: &SemanticSymbol) -> bool {
+    // Renames match on either side, so searching an old name still finds them.
+    let name_ok = params.name.as_ref().is_none_or(|pattern| {
+        name_matches(pattern, &symbol.name, params.name_match)
+            || name_matches(pattern, &record.symbol_name, params.name_match)
+    });
+
+    name_ok
+        && params.kind.as_ref().is_none_or(|k| *k == symbol.kind)
+        && params
+            .scope
+            .as_ref()
+            .is_none_or(|s| symbol.scope.as_ref() == Some(s))
+        && params
+            .structural_hash
+            .as_ref()
+            .is_none_or(|h| *h == symbol.structural_hash || *h == record.structural_hash)
+}
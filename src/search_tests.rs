@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::Engine;
+    use crate::models::RecordKind;
+    use crate::protocol::{NameMatch, SearchParams};
+    use crate::search::name_matches;
+    use crate::test_util;
+    use chrono::{Duration, Utc};
+
+    const V1: &str = "struct UserService;\nimpl UserService {\n    fn get_user(&self) -> u32 { 1 }\n    fn save(&self) {}\n}\n\nfn get_config() {}\n";
+    const V2: &str = "struct UserService;\nimpl UserService {\n    fn fetch_user(&self) -> u32 { 1 }\n    fn save(&self) {}\n}\n\nfn get_config() {}\n";
+
+    fn engine_with_history(dir: &tempfile::TempDir) -> Engine {
+        test_util::engine_with_versions(dir, "src/user.rs", &[V1, V2])
+    }
+
+    #[test]
+    fn test_search_renamed_functions_in_scope_last_week() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let result = engine
+            .search(&SearchParams {
+                kind: Some("function".to_string()),
+                scope: Some("UserService".to_string()),
+                record_kind: Some(RecordKind::Renamed),
+                since: Some(Utc::now() - Duration::days(7)),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(result.total, 1);
+        let hit = &result.hits[0];
+        assert_eq!(hit.path, "src/user.rs");
+        assert_eq!(hit.snapshot_id, 2);
+        assert_eq!(hit.symbol.name, "fetch_user");
+        assert_eq!(hit.record.symbol_name, "get_user");
+
+        let future = engine
+            .search(&SearchParams {
+                record_kind: Some(RecordKind::Renamed),
+                since: Some(Utc::now() + Duration::days(1)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(future.total, 0);
+    }
+
+    #[test]
+    fn test_search_by_name_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let exact = engine
+            .search(&SearchParams {
+                name: Some("save".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(exact.total, 1);
+
+        let prefix = engine
+            .search(&SearchParams {
+                name: Some("get_".to_string()),
+                name_match: NameMatch::Prefix,
+                ..Default::default()
+            })
+            .unwrap();
+        // get_user (added), get_user -> fetch_user (renamed), get_config (added)
+        assert_eq!(prefix.total, 3);
+
+        assert!(name_matches("usvc", "UserService", NameMatch::Fuzzy));
+        assert!(!name_matches("svcu", "UserService", NameMatch::Fuzzy));
+    }
+
+    #[test]
+    fn test_search_by_path_uses_only_that_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+        engine
+            .save("src/store.rs", "fn save() {}\nfn get_user() {}\n")
+            .unwrap();
+
+        let exact = engine
+            .search(&SearchParams {
+                path: Some("src/user.rs".to_string()),
+                name: Some("get_user".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        // The add and the rename away from it, newest first.
+        let kinds: Vec<&RecordKind> = exact.hits.iter().map(|h| &h.record.kind).collect();
+        assert_eq!(kinds, vec![&RecordKind::Renamed, &RecordKind::Added]);
+        assert!(exact.hits.iter().all(|h| h.path == "src/user.rs"));
+
+        let by_path = engine
+            .search(&SearchParams {
+                path: Some("src/store.rs".to_string()),
+                name: Some("sav".to_string()),
+                name_match: NameMatch::Prefix,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_path.total, 1);
+        assert_eq!(by_path.hits[0].path, "src/store.rs");
+    }
+
+    #[test]
+    fn test_search_pagination() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine_with_history(&dir);
+
+        let first = engine
+            .search(&SearchParams {
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(first.hits.len(), 2);
+        assert_eq!(SearchParams::default().limit, 50);
+        assert_eq!(first.next_offset, Some(2));
+        // Newest first: the rename from the second save comes before the initial adds.
+        assert!(matches!(first.hits[0].record.kind, RecordKind::Renamed));
+
+        let rest = engine
+            .search(&SearchParams {
+                offset: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(rest.hits.len(), first.total - 2);
+        assert_eq!(rest.next_offset, None);
+    }
+}
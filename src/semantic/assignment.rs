@@ -0,0 +1,185 @@
+/// Components with more cells than this are paired greedily instead; the
+/// Hungarian algorithm is cubic.
+const MAX_OPTIMAL_CELLS: usize = 250_000;
+
+/// Pairs rows with columns so that the total weight is as large as possible.
+///
+/// `weights[r][c]` is `None` where the pair is not allowed; rows and columns
+/// may stay unpaired. The result only depends on the weights and their
+/// order, and comes back sorted by row.
+///
+/// Rows and columns are split into independent groups (connected by allowed
+/// pairs) and each group is solved with the Hungarian algorithm. Groups too
+/// large for that fall back to picking the heaviest remaining pair, with ties
+/// going to the lowest row and column.
+pub fn max_weight_assignment(weights: &[Vec<Option<f64>>]) -> Vec<(usize, usize)> {
+    let rows = weights.len();
+    let cols = weights.first().map_or(0, Vec::len);
+
+    // Union-find over rows `0..rows` and columns `rows..rows + cols`.
+    let mut parent: Vec<usize> = (0..rows + cols).collect();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+    for (r, row) in weights.iter().enumerate() {
+        for (c, weight) in row.iter().enumerate() {
+            if weight.is_some() {
+                let (a, b) = (find(&mut parent, r), find(&mut parent, rows + c));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    let mut group_of = vec![usize::MAX; rows + cols];
+    for x in 0..rows + cols {
+        let root = find(&mut parent, x);
+        if group_of[root] == usize::MAX {
+            group_of[root] = groups.len();
+            groups.push((Vec::new(), Vec::new()));
+        }
+        let group = &mut groups[group_of[root]];
+        if x < rows {
+            group.0.push(x);
+        } else {
+            group.1.push(x - rows);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (group_rows, group_cols) in groups {
+        if group_rows.is_empty() || group_cols.is_empty() {
+            continue;
+        }
+        let sub: Vec<Vec<Option<f64>>> = group_rows
+            .iter()
+            .map(|&r| group_cols.iter().map(|&c| weights[r][c]).collect())
+            .collect();
+        let solved = if group_rows.len() * group_cols.len() <= MAX_OPTIMAL_CELLS {
+            hungarian(&sub)
+        } else {
+            greedy(&sub)
+        };
+        pairs.extend(
+            solved
+                .into_iter()
+                .map(|(r, c)| (group_rows[r], group_cols[c])),
+        );
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Optimal assignment. Every row also gets a private zero-weight "stay
+/// unpaired" column, so no row is forced onto a disallowed or losing pair.
+fn hungarian(weights: &[Vec<Option<f64>>]) -> Vec<(usize, usize)> {
+    let n = weights.len();
+    let cols = weights[0].len();
+    let m = cols + n;
+    // Larger than any sum of real costs, so disallowed pairs are never chosen.
+    let forbidden = 1.0
+        + weights
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|w| w.abs())
+            .sum::<f64>()
+            * 2.0;
+    let cost = |i: usize, j: usize| -> f64 {
+        if j < cols {
+            weights[i][j].map_or(forbidden, |w| -w)
+        } else if j - cols == i {
+            0.0
+        } else {
+            forbidden
+        }
+    };
+
+    // Shortest augmenting paths with potentials; rows and columns are 1-based
+    // here, index 0 being the virtual start.
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut row_of = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+    for i in 1..=n {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_to = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < min_to[j] {
+                    min_to[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_to[j] < delta {
+                    delta = min_to[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_to[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    (1..=cols)
+        .filter(|&j| row_of[j] != 0 && weights[row_of[j] - 1][j - 1].is_some())
+        .map(|j| (row_of[j] - 1, j - 1))
+        .collect()
+}
+
+fn greedy(weights: &[Vec<Option<f64>>]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(f64, usize, usize)> = weights
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(c, w)| w.map(|w| (w, r, c)))
+        })
+        .collect();
+    candidates.sort_by(|x, y| y.0.total_cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+
+    let mut row_taken = vec![false; weights.len()];
+    let mut col_taken = vec![false; weights.first().map_or(0, Vec::len)];
+    let mut pairs = Vec::new();
+    for (_, r, c) in candidates {
+        if !row_taken[r] && !col_taken[c] {
+            row_taken[r] = true;
+            col_taken[c] = true;
+            pairs.push((r, c));
+        }
+    }
+    pairs
+}
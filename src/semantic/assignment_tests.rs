@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use crate::semantic::assignment::max_weight_assignment;
+    use proptest::prelude::*;
+
+    fn total(weights: &[Vec<Option<f64>>], pairs: &[(usize, usize)]) -> f64 {
+        pairs.iter().map(|&(r, c)| weights[r][c].unwrap()).sum()
+    }
+
+    /// Best total over every assignment, by trying them all.
+    fn brute_force(weights: &[Vec<Option<f64>>], row: usize, taken: &mut Vec<bool>) -> f64 {
+        if row == weights.len() {
+            return 0.0;
+        }
+        let mut best = brute_force(weights, row + 1, taken);
+        for c in 0..taken.len() {
+            if let (false, Some(w)) = (taken[c], weights[row][c]) {
+                taken[c] = true;
+                best = best.max(w + brute_force(weights, row + 1, taken));
+                taken[c] = false;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_prefers_total_over_heaviest_pair() {
+        let weights = vec![vec![Some(0.95), Some(0.9)], vec![Some(0.8), None]];
+
+        assert_eq!(max_weight_assignment(&weights), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_disallowed_pairs_stay_unpaired() {
+        let weights = vec![
+            vec![None, None],
+            vec![None, Some(0.1)],
+            vec![None, Some(0.5)],
+        ];
+
+        assert_eq!(max_weight_assignment(&weights), vec![(2, 1)]);
+        assert!(max_weight_assignment(&[]).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_assignment_is_optimal(
+            weights in (1usize..5, 1usize..5).prop_flat_map(|(rows, cols)| {
+                prop::collection::vec(
+                    prop::collection::vec(prop::option::of(0.0f64..10.0), cols),
+                    rows,
+                )
+            })
+        ) {
+            let pairs = max_weight_assignment(&weights);
+
+            let mut rows: Vec<_> = pairs.iter().map(|p| p.0).collect();
+            let mut cols: Vec<_> = pairs.iter().map(|p| p.1).collect();
+            rows.dedup();
+            cols.sort_unstable();
+            cols.dedup();
+            prop_assert_eq!(rows.len(), pairs.len());
+            prop_assert_eq!(cols.len(), pairs.len());
+
+            let best = brute_force(&weights, 0, &mut vec![false; weights[0].len()]);
+            prop_assert!((total(&weights, &pairs) - best).abs() < 1e-9);
+        }
+    }
+}
@@ -1,88 +1,642 @@
-use crate::models::{RecordKind, SemanticRecord, SemanticSymbol};
-use std::collections::HashMap;
+use crate::models::{ChangeFlag, RecordKind, SemanticRecord, SemanticSymbol};
+use crate::semantic::assignment::max_weight_assignment;
+use crate::semantic::similarity::{name_similarity, similarity};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 pub struct SemanticDiffer;
 
+/// Thresholds for pairing a deleted symbol with an added one, and which
+/// modifications to report.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Minimum `similarity` for a `Renamed` record within one file
+    pub rename_similarity: f64,
+    /// Minimum `similarity` for a `Moved` record across files
+    pub move_similarity: f64,
+    /// Emit `Modified` records flagged `FormatOnly` for layout-only edits
+    pub report_format_only: bool,
+    /// Emit `Reordered` records for symbols that changed place in their scope
+    pub report_reorders: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            rename_similarity: 0.75,
+            move_similarity: 0.8,
+            report_format_only: false,
+            report_reorders: false,
+        }
+    }
+}
+
+/// Canonical symbol order: by position (outer before inner), then by content.
+/// The differ works in this order, so its output does not depend on the
+/// order of its input.
+fn canonical_order(a: &SemanticSymbol, b: &SemanticSymbol) -> Ordering {
+    a.start_byte
+        .cmp(&b.start_byte)
+        .then(b.end_byte.cmp(&a.end_byte))
+        .then_with(|| a.kind.cmp(&b.kind))
+        .then_with(|| a.scope.cmp(&b.scope))
+        .then_with(|| a.name.cmp(&b.name))
+        .then_with(|| a.structural_hash.cmp(&b.structural_hash))
+}
+
+pub(crate) fn canonical(symbols: &[SemanticSymbol]) -> Cow<'_, [SemanticSymbol]> {
+    if symbols.is_sorted_by(|a, b| canonical_order(a, b) != Ordering::Greater) {
+        Cow::Borrowed(symbols)
+    } else {
+        let mut sorted = symbols.to_vec();
+        sorted.sort_by(canonical_order);
+        Cow::Owned(sorted)
+    }
+}
+
+/// The symbols of one file version, as input to `SemanticDiffer::compare_project`.
+#[derive(Clone, Debug)]
+pub struct FileSymbols {
+    pub path: String,
+    pub symbols: Vec<SemanticSymbol>,
+}
+
+/// Identity of a symbol within one file version.
+///
+/// Two `new` methods in different `impl` blocks, or a struct and its `impl`
+/// sharing a name, are different symbols. Symbols sharing a full key (e.g.
+/// overloads) are told apart by their position among each other.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymbolKey {
+    pub scope: Option<String>,
+    pub kind: String,
+    pub name: String,
+}
+
+impl SymbolKey {
+    pub fn of(symbol: &SemanticSymbol) -> Self {
+        Self {
+            scope: symbol.scope.clone(),
+            kind: symbol.kind.clone(),
+            name: symbol.name.clone(),
+        }
+    }
+}
+
 impl SemanticDiffer {
+    /// `compare_with` using the default `DiffOptions`.
     pub fn compare(
         prev_symbols: &[SemanticSymbol],
         curr_symbols: &[SemanticSymbol],
         from_snapshot_id: Option<i64>,
         to_snapshot_id: i64,
     ) -> Vec<SemanticRecord> {
+        Self::compare_with(
+            prev_symbols,
+            curr_symbols,
+            from_snapshot_id,
+            to_snapshot_id,
+            &DiffOptions::default(),
+        )
+    }
+
+    /// Diffs two versions of one file.
+    ///
+    /// Symbols are matched by `SymbolKey`; a matched pair is `Modified` when
+    /// its structural hash differs or `classify` finds any change. Of the
+    /// rest, deleted and added symbols of the same kind are paired when they
+    /// share a structural hash or have a `similarity` of at least
+    /// `options.rename_similarity`; the score is kept as the record's
+    /// `confidence`. A pair in the same scope is `Renamed`, otherwise `Moved`
+    /// (e.g. a method moved from `impl A` to `impl B`).
+    ///
+    /// When several pairings are possible, the one with the best total
+    /// `pair_weight` wins. Symbols are processed in canonical order, so the
+    /// records do not depend on the order of either input.
+    ///
+    /// Symbols nested in others (by byte range) are diffed like any other;
+    /// their records name the enclosing symbol as `parent`, which is flagged
+    /// `ChildrenChanged` (see `link_parents`).
+    pub fn compare_with(
+        prev_symbols: &[SemanticSymbol],
+        curr_symbols: &[SemanticSymbol],
+        from_snapshot_id: Option<i64>,
+        to_snapshot_id: i64,
+        options: &DiffOptions,
+    ) -> Vec<SemanticRecord> {
+        let prev_symbols = &*canonical(prev_symbols);
+        let curr_symbols = &*canonical(curr_symbols);
         let mut records = Vec::new();
+        let record = |kind: RecordKind, symbol: &SemanticSymbol| SemanticRecord {
+            id: 0,
+            project_id: None,
+            from_snapshot_id,
+            to_snapshot_id,
+            symbol_name: symbol.name.clone(),
+            new_name: None,
+            scope: symbol.scope.clone(),
+            new_scope: None,
+            parent: None,
+            path: None,
+            new_path: None,
+            confidence: None,
+            kind,
+            structural_hash: symbol.structural_hash.clone(),
+            flags: vec![],
+            changes: vec![],
+        };
 
-        // Maps for efficient lookups
-        let prev_map: HashMap<String, &SemanticSymbol> =
-            prev_symbols.iter().map(|s| (s.name.clone(), s)).collect();
+        // curr index -> prev index
+        let matches = Self::match_by_identity(prev_symbols, curr_symbols);
+        let mut matched_prev = vec![false; prev_symbols.len()];
+        // (prev index, curr index) each record describes
+        let mut sides: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+        // (record index, curr index) of every Added record
+        let mut added = Vec::new();
 
         // 1. Identify Modified and Added
-        let mut matched_prev = std::collections::HashSet::new();
-
-        for curr in curr_symbols {
-            if let Some(prev) = prev_map.get(&curr.name) {
-                matched_prev.insert(curr.name.clone());
-                if prev.structural_hash != curr.structural_hash {
-                    records.push(SemanticRecord {
-                        id: 0,
-                        project_id: None,
-                        from_snapshot_id,
-                        to_snapshot_id,
-                        symbol_name: curr.name.clone(),
-                        new_name: None,
-                        kind: RecordKind::Modified,
-                        structural_hash: curr.structural_hash.clone(),
-                    });
+        for (i, curr) in curr_symbols.iter().enumerate() {
+            match matches.get(&i) {
+                Some(&p) => {
+                    matched_prev[p] = true;
+                    let prev = &prev_symbols[p];
+                    let flags = Self::classify(prev, curr, options);
+                    if prev.structural_hash != curr.structural_hash || !flags.is_empty() {
+                        let mut modified = record(RecordKind::Modified, curr);
+                        modified.flags = flags;
+                        records.push(modified);
+                        sides.push((Some(p), Some(i)));
+                    }
                 }
-            } else {
                 // Potential rename or purely added
-                records.push(SemanticRecord {
-                    id: 0,
-                    project_id: None,
-                    from_snapshot_id,
-                    to_snapshot_id,
-                    symbol_name: curr.name.clone(),
-                    new_name: None,
-                    kind: RecordKind::Added,
-                    structural_hash: curr.structural_hash.clone(),
-                });
-            }
-        }
-
-        // 2. Identify Deleted and Renamed
-        for prev in prev_symbols {
-            if !matched_prev.contains(&prev.name) {
-                // Check if this structural_hash exists in the Added records (Rename detection)
-                let mut found_rename = false;
-
-                // Sort records to ensure deterministic rename matching if multiple symbols have same hash
-                for record in records.iter_mut() {
-                    if matches!(record.kind, RecordKind::Added)
-                        && record.structural_hash == prev.structural_hash
-                    {
-                        record.kind = RecordKind::Renamed;
-                        record.new_name = Some(record.symbol_name.clone());
-                        record.symbol_name = prev.name.clone();
-                        found_rename = true;
-                        break;
+                None => {
+                    added.push((records.len(), i));
+                    records.push(record(RecordKind::Added, curr));
+                    sides.push((None, Some(i)));
+                }
+            }
+        }
+
+        // 2. Pair the rest: Renamed within a scope, Moved across scopes
+        let unmatched: Vec<usize> = (0..prev_symbols.len())
+            .filter(|&p| !matched_prev[p])
+            .collect();
+        let position = |i: usize, len: usize| i as f64 / len.max(1) as f64;
+        let weights: Vec<Vec<Option<f64>>> = unmatched
+            .iter()
+            .map(|&p| {
+                added
+                    .iter()
+                    .map(|&(_, c)| {
+                        Self::pair_weight(
+                            &prev_symbols[p],
+                            &curr_symbols[c],
+                            options.rename_similarity,
+                            position(p, prev_symbols.len()) - position(c, curr_symbols.len()),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for (row, col) in max_weight_assignment(&weights) {
+            let (p, (r, c)) = (unmatched[row], added[col]);
+            matched_prev[p] = true;
+            sides[r].0 = Some(p);
+
+            let (prev, curr) = (&prev_symbols[p], &curr_symbols[c]);
+            let r = &mut records[r];
+            r.confidence = Some(similarity(prev, curr));
+            r.symbol_name = prev.name.clone();
+            if prev.scope == curr.scope {
+                r.kind = RecordKind::Renamed;
+                r.new_name = Some(curr.name.clone());
+            } else {
+                r.kind = RecordKind::Moved;
+                r.new_name = (prev.name != curr.name).then(|| curr.name.clone());
+                r.scope = prev.scope.clone();
+                r.new_scope = curr.scope.clone();
+                r.flags = Self::classify(prev, curr, options);
+            }
+        }
+
+        // 3. Optionally report matched symbols that changed place among their siblings
+        if options.report_reorders {
+            for c in Self::reordered(prev_symbols, curr_symbols, &matches) {
+                records.push(record(RecordKind::Reordered, &curr_symbols[c]));
+                sides.push((matches.get(&c).copied(), Some(c)));
+            }
+        }
+
+        // 4. Identify Deleted
+        for (p, prev) in prev_symbols.iter().enumerate() {
+            if !matched_prev[p] {
+                records.push(record(RecordKind::Deleted, prev));
+                sides.push((Some(p), None));
+            }
+        }
+
+        // 5. Link nested symbols to their parents
+        Self::link_parents(prev_symbols, curr_symbols, &mut records, &sides, record);
+
+        records
+    }
+
+    /// Sets `parent` on records of nested symbols and flags the parents they
+    /// changed `ChildrenChanged`. A parent whose `own` hash is unchanged
+    /// changed only through its children and keeps no other flag; a parent
+    /// without a record (e.g. a field was renamed, which the structural hash
+    /// ignores) gets a `Modified` one. Added parents are left alone.
+    fn link_parents(
+        prev_symbols: &[SemanticSymbol],
+        curr_symbols: &[SemanticSymbol],
+        records: &mut Vec<SemanticRecord>,
+        sides: &[(Option<usize>, Option<usize>)],
+        record: impl Fn(RecordKind, &SemanticSymbol) -> SemanticRecord,
+    ) {
+        let prev_parents = Self::parents(prev_symbols);
+        let curr_parents = Self::parents(curr_symbols);
+        let mut prev_of_curr = vec![None; curr_symbols.len()];
+        for (&c, &p) in &Self::match_by_identity(prev_symbols, curr_symbols) {
+            prev_of_curr[c] = Some(p);
+        }
+        for &(p, c) in sides {
+            if let (Some(p), Some(c)) = (p, c) {
+                prev_of_curr[c] = Some(p);
+            }
+        }
+        let mut curr_of_prev = vec![None; prev_symbols.len()];
+        for (c, p) in prev_of_curr.iter().enumerate() {
+            if let Some(p) = *p {
+                curr_of_prev[p] = Some(c);
+            }
+        }
+        let parent_name = |c: usize| curr_parents[c].map(|cp| curr_symbols[cp].name.clone());
+
+        // curr indices of parents with a changed child
+        let mut changed = BTreeSet::new();
+        for (r, &(p, c)) in records.iter_mut().zip(sides) {
+            let prev_parent = p.and_then(|p| prev_parents[p]);
+            r.parent = match c {
+                Some(c) => parent_name(c),
+                None => prev_parent.map(|pp| prev_symbols[pp].name.clone()),
+            };
+            changed.extend(c.and_then(|c| curr_parents[c]));
+            changed.extend(prev_parent.and_then(|pp| curr_of_prev[pp]));
+        }
+
+        let mut record_of_curr: HashMap<usize, usize> = HashMap::new();
+        for (r, &(_, c)) in sides.iter().enumerate() {
+            if let Some(c) = c {
+                record_of_curr.entry(c).or_insert(r);
+            }
+        }
+        for c in changed {
+            let Some(p) = prev_of_curr[c] else {
+                continue;
+            };
+            let r = match record_of_curr.get(&c) {
+                Some(&r) if records[r].kind == RecordKind::Reordered => continue,
+                Some(&r) => &mut records[r],
+                None => {
+                    let mut modified = record(RecordKind::Modified, &curr_symbols[c]);
+                    modified.parent = parent_name(c);
+                    records.push(modified);
+                    records.last_mut().expect("record was just pushed")
+                }
+            };
+            let (old, new) = (&prev_symbols[p].hashes.own, &curr_symbols[c].hashes.own);
+            if !old.is_empty() && old == new {
+                r.flags.clear();
+            }
+            r.flags.push(ChangeFlag::ChildrenChanged);
+        }
+    }
+
+    /// Index of the innermost symbol strictly containing each symbol, for
+    /// symbols in canonical order.
+    pub(crate) fn parents(symbols: &[SemanticSymbol]) -> Vec<Option<usize>> {
+        let mut parents = Vec::with_capacity(symbols.len());
+        let mut open: Vec<usize> = Vec::new();
+        for symbol in symbols {
+            while let Some(&top) = open.last() {
+                let outer = &symbols[top];
+                if outer.start_byte <= symbol.start_byte
+                    && symbol.end_byte <= outer.end_byte
+                    && (outer.start_byte, outer.end_byte) != (symbol.start_byte, symbol.end_byte)
+                {
+                    break;
+                }
+                open.pop();
+            }
+            parents.push(open.last().copied());
+            open.push(parents.len() - 1);
+        }
+        parents
+    }
+
+    /// How strongly a deleted and an added symbol look like one symbol, or
+    /// `None` when they cannot be paired: kinds differ, or the hashes differ
+    /// and `similarity` is below `threshold`.
+    ///
+    /// Equal structural hashes outweigh everything else, then an unchanged
+    /// name (a re-scoped symbol), then the similarity itself. Name likeness,
+    /// an unchanged scope and a close relative position (`offset`, as a
+    /// fraction of the file) only break ties.
+    fn pair_weight(
+        prev: &SemanticSymbol,
+        curr: &SemanticSymbol,
+        threshold: f64,
+        offset: f64,
+    ) -> Option<f64> {
+        if prev.kind != curr.kind {
+            return None;
+        }
+        let exact = prev.structural_hash == curr.structural_hash;
+        let score = similarity(prev, curr);
+        if !exact && score < threshold {
+            return None;
+        }
+
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        Some(
+            4.0 * flag(exact)
+                + 2.0 * flag(prev.name == curr.name)
+                + score
+                + 1e-2 * name_similarity(&prev.name, &curr.name)
+                + 1e-3 * flag(prev.scope == curr.scope)
+                + 1e-4 * (1.0 - offset.abs()),
+        )
+    }
+
+    /// Current indices of matched symbols that changed place among the
+    /// symbols of their scope. The longest run that kept its relative order
+    /// stays put; everything else counts as reordered.
+    fn reordered(
+        prev_symbols: &[SemanticSymbol],
+        curr_symbols: &[SemanticSymbol],
+        matches: &HashMap<usize, usize>,
+    ) -> Vec<usize> {
+        let mut by_scope: BTreeMap<&Option<String>, Vec<(usize, usize)>> = BTreeMap::new();
+        for (&c, &p) in matches {
+            if prev_symbols[p].scope == curr_symbols[c].scope {
+                by_scope
+                    .entry(&curr_symbols[c].scope)
+                    .or_default()
+                    .push((p, c));
+            }
+        }
+
+        let mut reordered = Vec::new();
+        for pairs in by_scope.values_mut() {
+            pairs.sort_by_key(|&(p, _)| (prev_symbols[p].start_byte, p));
+            let positions: Vec<(usize, usize)> = pairs
+                .iter()
+                .map(|&(_, c)| (curr_symbols[c].start_byte, c))
+                .collect();
+
+            // Longest increasing subsequence of current positions, O(n^2).
+            let n = positions.len();
+            let mut length = vec![1; n];
+            let mut previous = vec![None; n];
+            for i in 0..n {
+                for j in 0..i {
+                    if positions[j] < positions[i] && length[j] + 1 > length[i] {
+                        length[i] = length[j] + 1;
+                        previous[i] = Some(j);
                     }
                 }
+            }
+            let mut in_order = vec![false; n];
+            let mut at = (0..n).max_by_key(|&i| (length[i], std::cmp::Reverse(i)));
+            while let Some(i) = at {
+                in_order[i] = true;
+                at = previous[i];
+            }
+
+            reordered.extend((0..n).filter(|&i| !in_order[i]).map(|i| positions[i].1));
+        }
+        reordered.sort_unstable();
+        reordered
+    }
+
+    /// Names the aspects that differ between two versions of one symbol.
+    ///
+    /// A token change that is not a signature or literal change (e.g. a local
+    /// variable renamed) counts as a body change. `FormatOnly` is reported
+    /// only when enabled in `options`. Symbols without `hashes` yield no flags.
+    pub fn classify(
+        prev: &SemanticSymbol,
+        curr: &SemanticSymbol,
+        options: &DiffOptions,
+    ) -> Vec<ChangeFlag> {
+        let (old, new) = (&prev.hashes, &curr.hashes);
+        let differs = |a: &String, b: &String| !a.is_empty() && !b.is_empty() && a != b;
+
+        let mut flags = Vec::new();
+        let signature = differs(&old.signature, &new.signature);
+        let literals = differs(&old.literals, &new.literals);
+        if signature {
+            flags.push(ChangeFlag::SignatureChanged);
+        }
+        if differs(&old.body, &new.body)
+            || (differs(&old.tokens, &new.tokens) && !signature && !literals)
+        {
+            flags.push(ChangeFlag::BodyChanged);
+        }
+        if literals {
+            flags.push(ChangeFlag::LiteralChanged);
+        }
+        if differs(&old.docs, &new.docs) {
+            flags.push(ChangeFlag::DocChanged);
+        }
+        if flags.is_empty() && options.report_format_only && differs(&old.text, &new.text) {
+            flags.push(ChangeFlag::FormatOnly);
+        }
+        flags
+    }
+
+    /// Diffs two versions of a whole project, file by file.
+    ///
+    /// Every record carries the `path` it applies to. A symbol deleted from one
+    /// file and added to another is reported once, as `Moved`, when both have
+    /// the same kind and either the same structural hash or a `similarity` of
+    /// at least `options.move_similarity`, which becomes the record's
+    /// `confidence`. Pairs are chosen like renames in `compare_with`, and
+    /// files are visited in path order, so input order does not matter.
+    /// Files are diffed in parallel.
+    pub fn compare_project(
+        prev_files: &[FileSymbols],
+        curr_files: &[FileSymbols],
+        from_snapshot_id: Option<i64>,
+        to_snapshot_id: i64,
+        options: &DiffOptions,
+    ) -> Vec<SemanticRecord> {
+        let mut paths: BTreeMap<&str, (&[SemanticSymbol], &[SemanticSymbol])> = BTreeMap::new();
+        for file in prev_files {
+            paths.entry(&file.path).or_default().0 = &file.symbols;
+        }
+        for file in curr_files {
+            paths.entry(&file.path).or_default().1 = &file.symbols;
+        }
 
-                if !found_rename {
-                    records.push(SemanticRecord {
-                        id: 0,
-                        project_id: None,
-                        from_snapshot_id,
-                        to_snapshot_id,
-                        symbol_name: prev.name.clone(),
-                        new_name: None,
-                        kind: RecordKind::Deleted,
-                        structural_hash: prev.structural_hash.clone(),
-                    });
+        let mut records = Vec::new();
+        // (record index, symbol) for every Deleted / Added record
+        let mut deleted = Vec::new();
+        let mut added = Vec::new();
+        let per_file: Vec<Vec<SemanticRecord>> = paths
+            .par_iter()
+            .map(|(_, (prev, curr))| {
+                Self::compare_with(prev, curr, from_snapshot_id, to_snapshot_id, options)
+            })
+            .collect();
+        for ((path, (prev, curr)), file_records) in paths.iter().zip(per_file) {
+            for mut record in file_records {
+                record.path = Some(path.to_string());
+                match record.kind {
+                    RecordKind::Deleted => {
+                        if let Some(symbol) = Self::symbol_of(prev, &record) {
+                            deleted.push((records.len(), symbol));
+                        }
+                    }
+                    RecordKind::Added => {
+                        if let Some(symbol) = Self::symbol_of(curr, &record) {
+                            added.push((records.len(), symbol));
+                        }
+                    }
+                    _ => {}
                 }
+                records.push(record);
+            }
+        }
+
+        // Positions mean nothing across files, so they never break ties here.
+        let weights: Vec<Vec<Option<f64>>> = deleted
+            .iter()
+            .map(|&(d, old)| {
+                added
+                    .iter()
+                    .map(|&(a, new)| {
+                        if records[d].path == records[a].path {
+                            return None;
+                        }
+                        Self::pair_weight(old, new, options.move_similarity, 0.0)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut consumed = vec![false; records.len()];
+        for (row, col) in max_weight_assignment(&weights) {
+            let ((d, old), (a, new_symbol)) = (deleted[row], added[col]);
+            consumed[d] = true;
+            consumed[a] = true;
+            let score = similarity(old, new_symbol);
+
+            let new = records[a].clone();
+            let moved = &mut records[d];
+            moved.kind = RecordKind::Moved;
+            if new.symbol_name != moved.symbol_name {
+                moved.new_name = Some(new.symbol_name);
             }
+            moved.new_scope = new.scope;
+            moved.parent = new.parent;
+            moved.new_path = new.path;
+            moved.structural_hash = new.structural_hash;
+            moved.confidence = Some(score);
         }
 
         records
+            .into_iter()
+            .enumerate()
+            .filter(|(i, r)| !consumed[*i] || r.kind == RecordKind::Moved)
+            .map(|(_, r)| r)
+            .collect()
+    }
+
+    /// The symbol an Added or Deleted record was built from.
+    pub(crate) fn symbol_of<'a>(
+        symbols: &'a [SemanticSymbol],
+        record: &SemanticRecord,
+    ) -> Option<&'a SemanticSymbol> {
+        symbols.iter().find(|s| {
+            s.name == record.symbol_name
+                && s.scope == record.scope
+                && s.structural_hash == record.structural_hash
+        })
+    }
+
+    /// Pairs symbols that share a `SymbolKey`. Duplicates of the same key are
+    /// paired in source order, so the result does not depend on hashing order.
+    pub(crate) fn match_by_identity(
+        prev_symbols: &[SemanticSymbol],
+        curr_symbols: &[SemanticSymbol],
+    ) -> HashMap<usize, usize> {
+        let prev_groups = Self::group_by_key(prev_symbols);
+        let curr_groups = Self::group_by_key(curr_symbols);
+
+        let mut matches = HashMap::new();
+        for (key, curr_indices) in &curr_groups {
+            if let Some(prev_indices) = prev_groups.get(key) {
+                for (&c, &p) in curr_indices.iter().zip(prev_indices) {
+                    matches.insert(c, p);
+                }
+            }
+        }
+        matches
+    }
+
+    /// For each symbol of `base`, the index of the same symbol in `side`:
+    /// matched by identity, or through a `Renamed` or `Moved` record.
+    pub(crate) fn counterparts(
+        base: &[SemanticSymbol],
+        side: &[SemanticSymbol],
+    ) -> Vec<Option<usize>> {
+        let mut map = vec![None; base.len()];
+        let mut used = vec![false; side.len()];
+        for (c, p) in Self::match_by_identity(base, side) {
+            map[p] = Some(c);
+            used[c] = true;
+        }
+
+        for record in Self::compare(base, side, None, 0) {
+            let new_scope = match record.kind {
+                RecordKind::Renamed => &record.scope,
+                RecordKind::Moved => &record.new_scope,
+                _ => continue,
+            };
+            let new_name = record.new_name.as_ref().unwrap_or(&record.symbol_name);
+            let p = (0..base.len()).find(|&p| {
+                map[p].is_none()
+                    && base[p].name == record.symbol_name
+                    && base[p].scope == record.scope
+            });
+            let c = (0..side.len()).find(|&c| {
+                !used[c]
+                    && side[c].name == *new_name
+                    && side[c].scope == *new_scope
+                    && side[c].structural_hash == record.structural_hash
+            });
+            if let (Some(p), Some(c)) = (p, c) {
+                map[p] = Some(c);
+                used[c] = true;
+            }
+        }
+        map
+    }
+
+    fn group_by_key(symbols: &[SemanticSymbol]) -> HashMap<SymbolKey, Vec<usize>> {
+        let mut groups: HashMap<SymbolKey, Vec<usize>> = HashMap::new();
+        for (i, symbol) in symbols.iter().enumerate() {
+            groups.entry(SymbolKey::of(symbol)).or_default().push(i);
+        }
+        for indices in groups.values_mut() {
+            indices.sort_by_key(|&i| (symbols[i].start_byte, i));
+        }
+        groups
     }
 }
@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod tests {
-    use crate::models::{DeltaKind, SemanticSymbol};
-    use crate::semantic::diff::SemanticDiffer;
+    use crate::models::{ChangeFlag, RecordKind, SemanticSymbol};
+    use crate::semantic::SemanticParser;
+    use crate::semantic::diff::{DiffOptions, FileSymbols, SemanticDiffer};
+    use bytes::Bytes;
+    use proptest::strategy::Strategy;
 
     fn create_mock_symbol(name: &str, hash: &str) -> SemanticSymbol {
         SemanticSymbol {
@@ -17,6 +20,8 @@ mod tests {
             start_byte: 0,
             end_byte: 0,
             parent_id: None,
+            shape: vec![],
+            hashes: Default::default(),
         }
     }
 
@@ -28,7 +33,7 @@ mod tests {
         let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
 
         assert_eq!(deltas.len(), 1);
-        assert!(matches!(deltas[0].kind, DeltaKind::Modified));
+        assert!(matches!(deltas[0].kind, RecordKind::Modified));
         assert_eq!(deltas[0].symbol_name, "func1");
     }
 
@@ -41,9 +46,10 @@ mod tests {
         let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
 
         assert_eq!(deltas.len(), 1);
-        assert!(matches!(deltas[0].kind, DeltaKind::Renamed));
+        assert!(matches!(deltas[0].kind, RecordKind::Renamed));
         assert_eq!(deltas[0].symbol_name, "old_name");
         assert_eq!(deltas[0].new_name, Some("new_name".to_string()));
+        assert_eq!(deltas[0].confidence, Some(1.0));
     }
 
     #[test]
@@ -56,12 +62,691 @@ mod tests {
         assert_eq!(deltas.len(), 2);
         let has_added = deltas
             .iter()
-            .any(|d| matches!(d.kind, DeltaKind::Added) && d.symbol_name == "added_func");
+            .any(|d| matches!(d.kind, RecordKind::Added) && d.symbol_name == "added_func");
         let has_deleted = deltas
             .iter()
-            .any(|d| matches!(d.kind, DeltaKind::Deleted) && d.symbol_name == "deleted_func");
+            .any(|d| matches!(d.kind, RecordKind::Deleted) && d.symbol_name == "deleted_func");
 
         assert!(has_added);
         assert!(has_deleted);
     }
+
+    fn create_scoped_symbol(
+        name: &str,
+        scope: Option<&str>,
+        hash: &str,
+        start: usize,
+    ) -> SemanticSymbol {
+        SemanticSymbol {
+            scope: scope.map(str::to_string),
+            start_byte: start,
+            end_byte: start + 10,
+            ..create_mock_symbol(name, hash)
+        }
+    }
+
+    #[test]
+    fn test_diff_same_name_in_different_scopes() {
+        let prev = vec![
+            create_scoped_symbol("new", Some("A"), "a1", 0),
+            create_scoped_symbol("new", Some("B"), "b1", 100),
+        ];
+        let curr = vec![
+            create_scoped_symbol("new", Some("A"), "a1", 0),
+            create_scoped_symbol("new", Some("B"), "b2", 100),
+        ];
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        assert_eq!(deltas.len(), 1);
+        assert!(matches!(deltas[0].kind, RecordKind::Modified));
+        assert_eq!(deltas[0].scope.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn test_diff_struct_and_impl_sharing_a_name() {
+        let mut strukt = create_mock_symbol("User", "s1");
+        strukt.kind = "struct".to_string();
+        let mut imp = create_mock_symbol("User", "i1");
+        imp.kind = "impl".to_string();
+        let mut imp_changed = imp.clone();
+        imp_changed.structural_hash = "i2".to_string();
+
+        let prev = vec![strukt.clone(), imp];
+        let curr = vec![strukt, imp_changed];
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        assert_eq!(deltas.len(), 1);
+        assert!(matches!(deltas[0].kind, RecordKind::Modified));
+        assert_eq!(deltas[0].structural_hash, "i2");
+    }
+
+    #[test]
+    fn test_diff_duplicates_matched_by_position() {
+        // Overloads: same scope, kind and name.
+        let prev = vec![
+            create_scoped_symbol("run", Some("Job"), "h1", 0),
+            create_scoped_symbol("run", Some("Job"), "h2", 50),
+        ];
+        let curr_forward = vec![
+            create_scoped_symbol("run", Some("Job"), "h1", 0),
+            create_scoped_symbol("run", Some("Job"), "h3", 50),
+        ];
+        let mut curr_reversed = curr_forward.clone();
+        curr_reversed.reverse();
+
+        for curr in [curr_forward, curr_reversed] {
+            let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+            assert_eq!(deltas.len(), 1);
+            assert!(matches!(deltas[0].kind, RecordKind::Modified));
+            assert_eq!(deltas[0].structural_hash, "h3");
+        }
+    }
+
+    fn file(path: &str, symbols: Vec<SemanticSymbol>) -> FileSymbols {
+        FileSymbols {
+            path: path.to_string(),
+            symbols,
+        }
+    }
+
+    fn parse_file(path: &str, source: &str) -> FileSymbols {
+        let mut parser = SemanticParser::new().unwrap();
+        let symbols = parser
+            .parse_symbols(&Bytes::from(source.to_string()), "rs", 0, None)
+            .unwrap();
+        file(path, symbols)
+    }
+
+    #[test]
+    fn test_project_diff_detects_move_by_hash() {
+        let prev = vec![
+            file("a.rs", vec![create_mock_symbol("helper", "h1")]),
+            file("b.rs", vec![create_mock_symbol("main", "m1")]),
+        ];
+        let curr = vec![
+            file("a.rs", vec![]),
+            file(
+                "b.rs",
+                vec![
+                    create_mock_symbol("main", "m1"),
+                    create_scoped_symbol("helper", Some("Util"), "h1", 20),
+                ],
+            ),
+        ];
+
+        let deltas =
+            SemanticDiffer::compare_project(&prev, &curr, Some(1), 2, &DiffOptions::default());
+
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].kind, RecordKind::Moved);
+        assert_eq!(deltas[0].symbol_name, "helper");
+        assert_eq!(deltas[0].new_name, None);
+        assert_eq!(deltas[0].path.as_deref(), Some("a.rs"));
+        assert_eq!(deltas[0].new_path.as_deref(), Some("b.rs"));
+        assert_eq!(deltas[0].scope, None);
+        assert_eq!(deltas[0].new_scope.as_deref(), Some("Util"));
+    }
+
+    #[test]
+    fn test_project_diff_detects_move_by_similarity() {
+        let body = "let total = items.iter().map(|i| i.price * i.quantity).sum::<f64>();\n\
+                    let tax = total * rate;\n\
+                    if tax > limit { return limit; }\n\
+                    for item in items { log(item); }";
+        let prev = vec![
+            parse_file(
+                "a.rs",
+                &format!("fn compute(items: &[Item]) -> f64 {{\n{}\ntax\n}}", body),
+            ),
+            parse_file("b.rs", ""),
+        ];
+        let curr = vec![
+            parse_file("a.rs", ""),
+            parse_file(
+                "b.rs",
+                &format!(
+                    "fn compute(items: &[Item]) -> f64 {{\n{}\ntax + 1.0\n}}",
+                    body
+                ),
+            ),
+        ];
+
+        let deltas =
+            SemanticDiffer::compare_project(&prev, &curr, Some(1), 2, &DiffOptions::default());
+
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].kind, RecordKind::Moved);
+        assert_eq!(deltas[0].symbol_name, "compute");
+        assert_eq!(deltas[0].new_path.as_deref(), Some("b.rs"));
+        assert_eq!(
+            deltas[0].structural_hash,
+            curr[1].symbols[0].structural_hash
+        );
+    }
+
+    #[test]
+    fn test_project_diff_keeps_unrelated_changes_per_file() {
+        let mut strukt = create_mock_symbol("Config", "c1");
+        strukt.kind = "struct".to_string();
+        let prev = vec![file("a.rs", vec![create_mock_symbol("Config", "c1")])];
+        let curr = vec![file("a.rs", vec![]), file("b.rs", vec![strukt])];
+
+        let deltas =
+            SemanticDiffer::compare_project(&prev, &curr, Some(1), 2, &DiffOptions::default());
+
+        assert_eq!(deltas.len(), 2);
+        assert!(
+            deltas
+                .iter()
+                .any(|d| d.kind == RecordKind::Deleted && d.path.as_deref() == Some("a.rs"))
+        );
+        assert!(
+            deltas
+                .iter()
+                .any(|d| d.kind == RecordKind::Added && d.path.as_deref() == Some("b.rs"))
+        );
+    }
+
+    const PRICE_BODY: &str = "let total = items.iter().map(|i| i.price * i.quantity).sum::<f64>();
+        let tax = total * rate;
+        if tax > limit { return limit; }
+        for item in items { log(item); }";
+
+    #[test]
+    fn test_diff_rename_with_body_edit() {
+        let prev = parse_file(
+            "a.rs",
+            &format!(
+                "fn price(items: &[Item]) -> f64 {{\n{}\ntax\n}}",
+                PRICE_BODY
+            ),
+        );
+        let curr = parse_file(
+            "a.rs",
+            &format!(
+                "fn total_price(items: &[Item]) -> f64 {{\n{}\ntax + 1.0\n}}",
+                PRICE_BODY
+            ),
+        );
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].kind, RecordKind::Renamed);
+        assert_eq!(deltas[0].symbol_name, "price");
+        assert_eq!(deltas[0].new_name.as_deref(), Some("total_price"));
+        let confidence = deltas[0].confidence.unwrap();
+        assert!((0.75..1.0).contains(&confidence), "{}", confidence);
+
+        let strict = DiffOptions {
+            rename_similarity: 1.0,
+            ..Default::default()
+        };
+        let deltas =
+            SemanticDiffer::compare_with(&prev.symbols, &curr.symbols, Some(1), 2, &strict);
+
+        assert_eq!(deltas.len(), 2);
+        assert!(deltas.iter().any(|d| d.kind == RecordKind::Added));
+        assert!(deltas.iter().any(|d| d.kind == RecordKind::Deleted));
+    }
+
+    #[test]
+    fn test_diff_dissimilar_symbols_are_not_renamed() {
+        let prev = parse_file(
+            "a.rs",
+            &format!(
+                "fn price(items: &[Item]) -> f64 {{\n{}\ntax\n}}",
+                PRICE_BODY
+            ),
+        );
+        let curr = parse_file(
+            "a.rs",
+            "fn greet(name: &str) {\n    println!(\"hi {}\", name);\n}",
+        );
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+
+        assert_eq!(deltas.len(), 2);
+        assert!(deltas.iter().all(|d| d.confidence.is_none()));
+    }
+
+    fn flags(old: &str, new: &str, options: &DiffOptions) -> Option<Vec<ChangeFlag>> {
+        let prev = parse_file("a.rs", old);
+        let curr = parse_file("a.rs", new);
+        let deltas =
+            SemanticDiffer::compare_with(&prev.symbols, &curr.symbols, Some(1), 2, options);
+        assert!(deltas.len() <= 1);
+        deltas.into_iter().next().map(|d| {
+            assert_eq!(d.kind, RecordKind::Modified);
+            d.flags
+        })
+    }
+
+    #[test]
+    fn test_diff_classifies_modifications() {
+        let options = DiffOptions::default();
+
+        assert_eq!(
+            flags(
+                "fn timeout() -> u64 { 30 }",
+                "fn timeout() -> u64 { 60 }",
+                &options
+            ),
+            Some(vec![ChangeFlag::LiteralChanged])
+        );
+        assert_eq!(
+            flags(
+                "/// Old docs\nfn f() {}",
+                "/// New docs\nfn f() {}",
+                &options
+            ),
+            Some(vec![ChangeFlag::DocChanged])
+        );
+        assert_eq!(
+            flags("fn f(a: u32) {}", "fn f(a: u64) {}", &options),
+            Some(vec![ChangeFlag::SignatureChanged])
+        );
+        assert_eq!(
+            flags("fn f() { a(); }", "fn f() { a(); b(); }", &options),
+            Some(vec![ChangeFlag::BodyChanged])
+        );
+        assert_eq!(
+            flags(
+                "fn f() { let x = 1; x }",
+                "fn f() { let y = 1; y }",
+                &options
+            ),
+            Some(vec![ChangeFlag::BodyChanged])
+        );
+    }
+
+    #[test]
+    fn test_diff_format_only_is_opt_in() {
+        let old = "fn f() { a(); }";
+        let new = "fn f() {\n    a();\n}";
+
+        assert_eq!(flags(old, new, &DiffOptions::default()), None);
+
+        let options = DiffOptions {
+            report_format_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            flags(old, new, &options),
+            Some(vec![ChangeFlag::FormatOnly])
+        );
+    }
+
+    #[test]
+    fn test_diff_method_moved_between_impls() {
+        let prev = parse_file(
+            "a.rs",
+            "struct A;\nstruct B;\nimpl A {\n    fn reset(&mut self) { self.count = 0; }\n}\nimpl B {}\n",
+        );
+        let curr = parse_file(
+            "a.rs",
+            "struct A;\nstruct B;\nimpl A {}\nimpl B {\n    fn reset(&mut self) { self.count = 0; }\n}\n",
+        );
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+        let moved: Vec<_> = deltas
+            .iter()
+            .filter(|d| d.kind == RecordKind::Moved)
+            .collect();
+
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].symbol_name, "reset");
+        assert_eq!(moved[0].new_name, None);
+        assert_eq!(moved[0].scope.as_deref(), Some("A"));
+        assert_eq!(moved[0].new_scope.as_deref(), Some("B"));
+        assert_eq!(moved[0].confidence, Some(1.0));
+        assert!(
+            !deltas
+                .iter()
+                .any(|d| matches!(d.kind, RecordKind::Added | RecordKind::Deleted))
+        );
+    }
+
+    #[test]
+    fn test_diff_reorder_is_opt_in() {
+        let prev = vec![
+            create_scoped_symbol("a", None, "ha", 0),
+            create_scoped_symbol("b", None, "hb", 20),
+            create_scoped_symbol("c", None, "hc", 40),
+        ];
+        let curr = vec![
+            create_scoped_symbol("b", None, "hb", 0),
+            create_scoped_symbol("c", None, "hc", 20),
+            create_scoped_symbol("a", None, "ha", 40),
+        ];
+
+        assert!(SemanticDiffer::compare(&prev, &curr, Some(1), 2).is_empty());
+
+        let options = DiffOptions {
+            report_reorders: true,
+            ..Default::default()
+        };
+        let deltas = SemanticDiffer::compare_with(&prev, &curr, Some(1), 2, &options);
+
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].kind, RecordKind::Reordered);
+        assert_eq!(deltas[0].symbol_name, "a");
+    }
+
+    const USER: &str = "struct User {\n    id: u64,\n    name: String,\n}\n";
+
+    #[test]
+    fn test_parser_nests_fields_under_their_struct() {
+        let parsed = parse_file("a.rs", USER);
+
+        let names: Vec<_> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["User", "id", "name"]);
+        assert_eq!(parsed.symbols[0].parent_id, None);
+        assert_eq!(parsed.symbols[1].parent_id, Some(parsed.symbols[0].id));
+        assert_eq!(parsed.symbols[2].parent_id, Some(parsed.symbols[0].id));
+        assert_eq!(parsed.symbols[1].kind, "field");
+        assert_eq!(parsed.symbols[1].scope.as_deref(), Some("User"));
+    }
+
+    #[test]
+    fn test_diff_reports_parent_modified_by_child() {
+        let prev = parse_file("a.rs", USER);
+        let curr = parse_file(
+            "a.rs",
+            "struct User {\n    id: u64,\n    name: String,\n    /// Contact address\n    email: String,\n}\n",
+        );
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].kind, RecordKind::Modified);
+        assert_eq!(deltas[0].symbol_name, "User");
+        assert_eq!(deltas[0].flags, [ChangeFlag::ChildrenChanged]);
+        assert_eq!(deltas[0].parent, None);
+        assert_eq!(deltas[1].kind, RecordKind::Added);
+        assert_eq!(deltas[1].symbol_name, "email");
+        assert_eq!(deltas[1].parent.as_deref(), Some("User"));
+    }
+
+    #[test]
+    fn test_diff_reports_enum_modified_by_renamed_variant() {
+        let prev = parse_file("a.rs", "enum State {\n    Idle,\n    Busy(u32),\n}\n");
+        let curr = parse_file("a.rs", "enum State {\n    Idle,\n    Working(u32),\n}\n");
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].kind, RecordKind::Modified);
+        assert_eq!(deltas[0].symbol_name, "State");
+        assert_eq!(deltas[0].flags, [ChangeFlag::ChildrenChanged]);
+        assert_eq!(deltas[1].kind, RecordKind::Renamed);
+        assert_eq!(deltas[1].new_name.as_deref(), Some("Working"));
+        assert_eq!(deltas[1].scope.as_deref(), Some("State"));
+        assert_eq!(deltas[1].parent.as_deref(), Some("State"));
+    }
+
+    fn parse_symbols(extension: &str, source: &str) -> Vec<SemanticSymbol> {
+        let mut parser = SemanticParser::new().unwrap();
+        parser
+            .parse_symbols(&Bytes::from(source.to_string()), extension, 0, None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parser_nests_members_in_other_languages() {
+        let cases = [
+            ("py", "class User:\n    email = ''\n", "class", "field"),
+            ("js", "class User {\n  email = '';\n}\n", "class", "field"),
+            (
+                "ts",
+                "class User {\n  email: string;\n}\n",
+                "class",
+                "field",
+            ),
+            (
+                "ts",
+                "interface User {\n  email: string;\n}\n",
+                "interface",
+                "field",
+            ),
+            ("ts", "enum User {\n  email = 1,\n}\n", "enum", "variant"),
+            (
+                "go",
+                "package m\ntype User struct {\n\temail string\n}\n",
+                "type",
+                "field",
+            ),
+            (
+                "java",
+                "class User {\n  String email;\n}\n",
+                "class",
+                "field",
+            ),
+            ("java", "enum User {\n  email\n}\n", "enum", "variant"),
+            (
+                "php",
+                "<?php\nclass User {\n  public $email;\n}\n",
+                "class",
+                "field",
+            ),
+        ];
+
+        for (extension, source, parent_kind, child_kind) in cases {
+            let symbols = parse_symbols(extension, source);
+            let summary: Vec<_> = symbols
+                .iter()
+                .map(|s| (s.kind.as_str(), s.name.as_str()))
+                .collect();
+            assert_eq!(
+                summary,
+                [(parent_kind, "User"), (child_kind, "email")],
+                "{}",
+                source
+            );
+            assert_eq!(symbols[1].parent_id, Some(symbols[0].id), "{}", source);
+            assert_eq!(symbols[1].scope.as_deref(), Some("User"), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_java_class_modified_by_added_field() {
+        let prev = parse_symbols("java", "class User {\n  long id;\n}\n");
+        let curr = parse_symbols("java", "class User {\n  long id;\n  String email;\n}\n");
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].kind, RecordKind::Modified);
+        assert_eq!(deltas[0].symbol_name, "User");
+        assert_eq!(deltas[0].flags, [ChangeFlag::ChildrenChanged]);
+        assert_eq!(deltas[1].kind, RecordKind::Added);
+        assert_eq!(deltas[1].symbol_name, "email");
+        assert_eq!(deltas[1].parent.as_deref(), Some("User"));
+    }
+
+    #[test]
+    fn test_diff_keeps_members_of_different_classes_apart() {
+        let prev = parse_symbols("java", "class A {\n  int id;\n}\nclass B {\n  int id;\n}\n");
+        let curr = parse_symbols("java", "class B {\n  int id;\n}\n");
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        let summary: Vec<_> = deltas
+            .iter()
+            .map(|d| (d.kind.clone(), d.symbol_name.as_str(), d.scope.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (RecordKind::Deleted, "A", None),
+                (RecordKind::Deleted, "id", Some("A")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_keeps_parent_flags_for_own_edits() {
+        let prev = parse_file("a.rs", USER);
+        let curr = parse_file("a.rs", "struct User<T> {\n    id: u64,\n    name: T,\n}\n");
+
+        let deltas = SemanticDiffer::compare(&prev.symbols, &curr.symbols, Some(1), 2);
+        let user = deltas.iter().find(|d| d.symbol_name == "User").unwrap();
+
+        assert!(user.flags.contains(&ChangeFlag::SignatureChanged));
+        assert!(user.flags.contains(&ChangeFlag::ChildrenChanged));
+        assert!(
+            deltas
+                .iter()
+                .any(|d| d.symbol_name == "name" && d.kind == RecordKind::Modified)
+        );
+    }
+
+    fn shaped_symbol(name: &str, hash: &str, shape: &[std::ops::Range<u64>]) -> SemanticSymbol {
+        let mut shape: Vec<u64> = shape.iter().cloned().flatten().collect();
+        shape.sort_unstable();
+        SemanticSymbol {
+            shape,
+            ..create_mock_symbol(name, hash)
+        }
+    }
+
+    #[test]
+    fn test_diff_rename_assignment_is_globally_optimal() {
+        // Greedy would pair x -> p (0.95) and leave y unpaired, since y -> q
+        // is below the threshold; x -> q (0.9) plus y -> p (0.8) is better.
+        let prev = vec![
+            shaped_symbol("x", "hx", &[0..10, 10..19, 50..51]),
+            shaped_symbol("y", "hy", &[10..20, 0..6, 60..64]),
+        ];
+        let curr = vec![
+            shaped_symbol("p", "hp", &[0..10, 10..20]),
+            shaped_symbol("q", "hq", &[0..10, 10..18, 70..72]),
+        ];
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        let mut pairs: Vec<(String, String)> = deltas
+            .iter()
+            .map(|d| {
+                assert_eq!(d.kind, RecordKind::Renamed);
+                (d.symbol_name.clone(), d.new_name.clone().unwrap())
+            })
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("x".to_string(), "q".to_string()),
+                ("y".to_string(), "p".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_identical_renames_pair_by_name() {
+        let prev = vec![
+            create_scoped_symbol("get_user", None, "getter", 0),
+            create_scoped_symbol("get_order", None, "getter", 20),
+        ];
+        let curr = vec![
+            create_scoped_symbol("fetch_order", None, "getter", 0),
+            create_scoped_symbol("fetch_user", None, "getter", 20),
+        ];
+
+        let deltas = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+        assert_eq!(deltas.len(), 2);
+        for d in &deltas {
+            assert_eq!(d.kind, RecordKind::Renamed);
+            let new_name = d.new_name.as_deref().unwrap();
+            assert_eq!(
+                d.symbol_name.trim_start_matches("get_"),
+                new_name.trim_start_matches("fetch_")
+            );
+        }
+    }
+
+    fn ambiguous_versions() -> (Vec<SemanticSymbol>, Vec<SemanticSymbol>) {
+        let prev = vec![
+            create_scoped_symbol("id", Some("User"), "getter", 0),
+            create_scoped_symbol("id", Some("Order"), "getter", 40),
+            create_scoped_symbol("name", Some("User"), "getter", 80),
+            create_scoped_symbol("total", Some("Order"), "getter", 120),
+            create_scoped_symbol("run", None, "r1", 160),
+            create_scoped_symbol("run", None, "r2", 200),
+            create_scoped_symbol("wrap", None, "wrapper", 240),
+            create_scoped_symbol("wrap2", None, "wrapper", 280),
+        ];
+        let curr = vec![
+            create_scoped_symbol("user_id", Some("User"), "getter", 0),
+            create_scoped_symbol("id", Some("Order"), "getter", 40),
+            create_scoped_symbol("name", Some("Account"), "getter", 80),
+            create_scoped_symbol("sum", Some("Order"), "getter", 120),
+            create_scoped_symbol("run", None, "r1", 160),
+            create_scoped_symbol("run", None, "r3", 200),
+            create_scoped_symbol("wrapped", None, "wrapper", 240),
+            create_scoped_symbol("wrapper", None, "wrapper", 280),
+            create_scoped_symbol("extra", None, "getter", 320),
+        ];
+        (prev, curr)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_diff_does_not_depend_on_input_order(
+            prev in proptest::strategy::Just(ambiguous_versions().0).prop_shuffle(),
+            curr in proptest::strategy::Just(ambiguous_versions().1).prop_shuffle(),
+        ) {
+            let (sorted_prev, sorted_curr) = ambiguous_versions();
+            let expected = SemanticDiffer::compare(&sorted_prev, &sorted_curr, Some(1), 2);
+            let actual = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+
+            proptest::prop_assert_eq!(
+                serde_json::to_value(&actual).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+        }
+
+        #[test]
+        fn prop_project_diff_does_not_depend_on_input_order(
+            prev in proptest::strategy::Just(ambiguous_versions().0).prop_shuffle(),
+            curr in proptest::strategy::Just(ambiguous_versions().1).prop_shuffle(),
+            swap_files in proptest::bool::ANY,
+        ) {
+            let split = |symbols: Vec<SemanticSymbol>, other: &str| {
+                let (here, there): (Vec<_>, Vec<_>) =
+                    symbols.into_iter().partition(|s| s.start_byte < 160);
+                vec![file("a.rs", here), file(other, there)]
+            };
+            let options = DiffOptions::default();
+            let (sorted_prev, sorted_curr) = ambiguous_versions();
+            let expected = SemanticDiffer::compare_project(
+                &split(sorted_prev, "b.rs"),
+                &split(sorted_curr, "c.rs"),
+                Some(1),
+                2,
+                &options,
+            );
+
+            let mut prev_files = split(prev, "b.rs");
+            if swap_files {
+                prev_files.reverse();
+            }
+            let actual = SemanticDiffer::compare_project(
+                &prev_files,
+                &split(curr, "c.rs"),
+                Some(1),
+                2,
+                &options,
+            );
+
+            proptest::prop_assert_eq!(
+                serde_json::to_value(&actual).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+        }
+    }
 }
@@ -1,4 +1,6 @@
+use crate::models::SymbolHashes;
 use blake3::Hasher;
+use std::ops::Range;
 use tree_sitter::{Node, TreeCursor};
 
 /// Generates a structural hash of a code block, ignoring variable names and literals
@@ -16,36 +18,178 @@ impl StructuralFingerprinter {
         hasher.finalize().to_hex().to_string()
     }
 
+    /// Computes the per-aspect hashes used to classify a modification.
+    ///
+    /// The signature covers every token outside the node's `body` field; the
+    /// body is hashed structurally, like `compute`. Docs are the comments
+    /// inside the node plus those directly above it (attributes may sit in
+    /// between). `own` depends on the nested symbols and is left empty; see
+    /// `own`.
+    pub fn hashes(node: Node, source: &[u8]) -> SymbolHashes {
+        let body = node.child_by_field_name("body");
+        let mut hashers = AspectHashers {
+            signature: Hasher::new(),
+            literals: Hasher::new(),
+            docs: Hasher::new(),
+            tokens: Hasher::new(),
+            body: body.map(|b| b.byte_range()),
+            source,
+        };
+
+        let mut docs = Vec::new();
+        let mut above = node.prev_sibling();
+        while let Some(sibling) = above {
+            if sibling.kind().ends_with("comment") {
+                docs.push(sibling);
+            } else if sibling.kind() != "attribute_item" {
+                break;
+            }
+            above = sibling.prev_sibling();
+        }
+        for doc in docs.into_iter().rev() {
+            hashers.docs.update(&source[doc.byte_range()]);
+        }
+
+        hashers.visit(node);
+
+        SymbolHashes {
+            signature: short_hex(&hashers.signature),
+            body: body
+                .map(|b| short_hex_of(Self::compute(b, source)))
+                .unwrap_or_default(),
+            literals: short_hex(&hashers.literals),
+            docs: short_hex(&hashers.docs),
+            tokens: short_hex(&hashers.tokens),
+            text: short_hex_of(&source[node.byte_range()]),
+            own: String::new(),
+        }
+    }
+
+    /// Hashes the tokens of `node` outside the `nested` symbol ranges, for
+    /// `SymbolHashes::own`.
+    ///
+    /// Comments and attributes directly above a nested symbol go with it, and
+    /// commas are left out, so adding, removing or reordering children leaves
+    /// the hash unchanged.
+    pub fn own(node: Node, source: &[u8], nested: &[Range<usize>]) -> String {
+        let mut hasher = Hasher::new();
+        Self::own_tokens(node, source, nested, &mut hasher);
+        short_hex(&hasher)
+    }
+
+    fn own_tokens(node: Node, source: &[u8], nested: &[Range<usize>], hasher: &mut Hasher) {
+        if node.child_count() == 0 {
+            if node.kind() != "," {
+                hasher.update(&source[node.byte_range()]).update(b"\0");
+            }
+            return;
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        // Walk backwards so leading comments and attributes can see whether
+        // the item they decorate is nested.
+        let mut skip = vec![false; children.len()];
+        let mut attached = false;
+        for (i, child) in children.iter().enumerate().rev() {
+            if nested.contains(&child.byte_range()) {
+                attached = true;
+            } else if !(child.kind().ends_with("comment") || child.kind() == "attribute_item") {
+                attached = false;
+            }
+            skip[i] = attached;
+        }
+
+        for (child, skip) in children.into_iter().zip(skip) {
+            if !skip {
+                Self::own_tokens(child, source, nested, hasher);
+            }
+        }
+    }
+
+    /// Hashes every named, non-leaf subtree below `node` with the same
+    /// normalization as `compute`, sorted so it can be compared as a multiset.
+    ///
+    /// Two symbols sharing most of their shape have mostly the same structure,
+    /// even when a small edit makes their `compute` hashes differ.
+    pub fn shape(node: Node) -> Vec<u64> {
+        let mut shape = Vec::new();
+        let mut cursor = node.walk();
+
+        if cursor.goto_first_child() {
+            loop {
+                Self::subtree(&mut cursor, &mut shape);
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        shape.sort_unstable();
+        shape
+    }
+
+    /// Hashes the subtree under the cursor bottom-up, recording named inner
+    /// nodes in `shape`. Comments hash to nothing. The cursor is left where it started.
+    fn subtree(cursor: &mut TreeCursor, shape: &mut Vec<u64>) -> Option<blake3::Hash> {
+        let node = cursor.node();
+        let token = Self::token(node)?;
+        let mut hasher = Hasher::new();
+        hasher.update(token);
+
+        if !cursor.goto_first_child() {
+            return Some(hasher.finalize());
+        }
+        loop {
+            if let Some(child) = Self::subtree(cursor, shape) {
+                hasher.update(child.as_bytes());
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        cursor.goto_parent();
+
+        let hash = hasher.finalize();
+        if node.is_named() {
+            let (prefix, _) = hash.as_bytes().split_at(8);
+            shape.push(u64::from_le_bytes(
+                prefix.try_into().expect("prefix is 8 bytes"),
+            ));
+        }
+        Some(hash)
+    }
+
+    /// The normalized token a single node contributes to a hash, if any.
+    fn token(node: Node) -> Option<&'static [u8]> {
+        if !node.is_named() {
+            // Anonymous nodes (keywords, operators, punctuation) define structure
+            return Some(node.kind().as_bytes());
+        }
+
+        // Named nodes are normalized to ignore variable/literal identity
+        match node.kind() {
+            "identifier" | "field_identifier" | "type_identifier" => Some(b"|ID|"),
+            "string_literal" | "integer_literal" | "float_literal" | "boolean_literal" => {
+                Some(b"|LIT|")
+            }
+            // Skip comments (`comment`, `line_comment`, ...) entirely for structural comparison
+            kind if kind.ends_with("comment") => None,
+            // For structural nodes (blocks, loops, etc), hash the type
+            kind => Some(kind.as_bytes()),
+        }
+    }
+
     fn traverse(cursor: &mut TreeCursor, hasher: &mut Hasher) {
         let mut reached_root = false;
         while !reached_root {
-            let node = cursor.node();
-            let kind = node.kind();
-
-            if !node.is_named() {
-                // Anonymous nodes (keywords, operators, punctuation) define structure
-                hasher.update(kind.as_bytes());
-            } else {
-                // Named nodes are normalized to ignore variable/literal identity
-                match kind {
-                    "identifier" | "field_identifier" | "type_identifier" => {
-                        hasher.update(b"|ID|");
-                    }
-                    "string_literal" | "integer_literal" | "float_literal" | "boolean_literal" => {
-                        hasher.update(b"|LIT|");
-                    }
-                    "comment" => {
-                        // Skip comments entirely for structural comparison
-                    }
-                    _ => {
-                        // For structural nodes (blocks, loops, etc), hash the type
-                        hasher.update(kind.as_bytes());
-                    }
-                }
+            let token = Self::token(cursor.node());
+            if let Some(token) = token {
+                hasher.update(token);
             }
 
-            // Depth-first traversal
-            if cursor.goto_first_child() {
+            // Depth-first traversal, not descending into comments
+            if token.is_some() && cursor.goto_first_child() {
                 continue;
             }
 
@@ -67,4 +211,66 @@ impl StructuralFingerprinter {
     }
 }
 
+/// Hex digits kept from each `SymbolHashes` entry. They are only compared
+/// between versions of one symbol, so 64 bits is plenty.
+const SHORT_HASH_HEX: usize = 16;
+
+fn short_hex(hasher: &Hasher) -> String {
+    let mut hex = hasher.finalize().to_hex().to_string();
+    hex.truncate(SHORT_HASH_HEX);
+    hex
+}
+
+fn short_hex_of(data: impl AsRef<[u8]>) -> String {
+    let mut hasher = Hasher::new();
+    hasher.update(data.as_ref());
+    short_hex(&hasher)
+}
+
+/// Node kinds whose text is a literal value, across the supported grammars.
+fn is_literal(kind: &str) -> bool {
+    kind.ends_with("literal")
+        || matches!(
+            kind,
+            "string" | "number" | "integer" | "float" | "true" | "false" | "char"
+        )
+}
+
+struct AspectHashers<'a> {
+    signature: Hasher,
+    literals: Hasher,
+    docs: Hasher,
+    tokens: Hasher,
+    body: Option<Range<usize>>,
+    source: &'a [u8],
+}
+
+impl AspectHashers<'_> {
+    fn visit(&mut self, node: Node) {
+        let text = &self.source[node.byte_range()];
+        if node.kind().ends_with("comment") {
+            self.docs.update(text);
+            return;
+        }
+        if node.is_named() && is_literal(node.kind()) {
+            self.literals.update(text).update(b"\0");
+        }
+        if node.child_count() == 0 {
+            self.tokens.update(text).update(b"\0");
+            let in_body = self
+                .body
+                .as_ref()
+                .is_some_and(|b| b.contains(&node.start_byte()));
+            if !in_body {
+                self.signature.update(text).update(b"\0");
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.visit(child);
+        }
+    }
+}
+
 pub type Fingerprinter = StructuralFingerprinter;
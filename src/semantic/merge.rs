@@ -0,0 +1,611 @@
+use crate::error::{SrpError, SrpResult};
+use crate::models::SemanticSymbol;
+use crate::semantic::SemanticParser;
+use crate::semantic::diff::{SemanticDiffer, SymbolKey, canonical};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, DiffTag, capture_diff_slices};
+use std::collections::HashMap;
+use std::ops::Range;
+
+const BASE: usize = 0;
+const OURS: usize = 1;
+const THEIRS: usize = 2;
+
+/// Delimits a symbol placeholder in a region skeleton. The source's own
+/// `TOKEN` bytes are escaped as an empty placeholder.
+const TOKEN: u8 = 0x01;
+
+/// One version of a file: its content and the symbols parsed from it.
+#[derive(Clone, Copy, Debug)]
+pub struct FileVersion<'a> {
+    pub content: &'a [u8],
+    pub symbols: &'a [SemanticSymbol],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictKind {
+    /// Both sides changed the symbol, differently
+    BothModified,
+    /// Ours changed the symbol, theirs deleted it
+    ModifiedDeleted,
+    /// Ours deleted the symbol, theirs changed it
+    DeletedModified,
+    /// Both sides added a symbol of the same scope, kind and name, differently
+    BothAdded,
+    /// Both sides changed the same text outside any nested symbol
+    Text,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub kind: ConflictKind,
+    /// The conflicting symbol; for `Text`, the symbol around the text (unset
+    /// at file level)
+    pub symbol_name: Option<String>,
+    pub scope: Option<String>,
+    /// Each side's text, unset where that side deleted it
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MergeResult {
+    /// The merged file. Conflicts are left in it between `<<<<<<< ours`,
+    /// `=======` and `>>>>>>> theirs` lines.
+    pub content: Bytes,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Parses three versions of a file and merges them; see `merge`.
+pub fn merge_sources(
+    parser: &mut SemanticParser,
+    extension: &str,
+    base: &Bytes,
+    ours: &Bytes,
+    theirs: &Bytes,
+) -> SrpResult<MergeResult> {
+    let base_symbols = parser.parse_symbols(base, extension, 0, None)?;
+    let our_symbols = parser.parse_symbols(ours, extension, 0, None)?;
+    let their_symbols = parser.parse_symbols(theirs, extension, 0, None)?;
+    merge(
+        FileVersion {
+            content: base,
+            symbols: &base_symbols,
+        },
+        FileVersion {
+            content: ours,
+            symbols: &our_symbols,
+        },
+        FileVersion {
+            content: theirs,
+            symbols: &their_symbols,
+        },
+    )
+}
+
+/// Three-way merge of a file, symbol by symbol.
+///
+/// Symbols are matched to their base version by `SemanticDiffer::compare`
+/// (identity, renames and moves). A symbol changed on one side only takes
+/// that side's text; one changed on both sides to different texts is merged
+/// recursively through its nested symbols if it has any, and conflicts
+/// otherwise. Text around the symbols (imports, comments, where each symbol
+/// sits) is merged line by line with every symbol reduced to one
+/// placeholder line, so edits to adjacent symbols never collide.
+pub fn merge(base: FileVersion, ours: FileVersion, theirs: FileVersion) -> SrpResult<MergeResult> {
+    let versions = [base, ours, theirs].map(Version::new);
+    let counterparts = [
+        (0..versions[BASE].symbols.len()).map(Some).collect(),
+        SemanticDiffer::counterparts(&versions[BASE].symbols, &versions[OURS].symbols),
+        SemanticDiffer::counterparts(&versions[BASE].symbols, &versions[THEIRS].symbols),
+    ];
+    let mut base_of: [Vec<Option<usize>>; 3] =
+        versions.each_ref().map(|v| vec![None; v.symbols.len()]);
+    for (side, map) in counterparts.iter().enumerate() {
+        for (b, c) in map.iter().enumerate() {
+            if let Some(c) = *c {
+                base_of[side][c] = Some(b);
+            }
+        }
+    }
+
+    let mut merger = Merger {
+        versions,
+        base_of,
+        conflicts: Vec::new(),
+    };
+    let ranges = merger.versions.each_ref().map(|v| 0..v.content.len());
+    let roots = merger.versions.each_ref().map(|v| v.roots.clone());
+    let content = merger.merge_region(ranges, roots, None)?;
+
+    Ok(MergeResult {
+        content: Bytes::from(content),
+        conflicts: merger.conflicts,
+    })
+}
+
+struct Version<'a> {
+    content: &'a [u8],
+    /// In canonical order
+    symbols: Vec<SemanticSymbol>,
+    /// Direct children of each symbol, in source order. Siblings sharing one
+    /// byte range (`int a, b;`) are one piece of text, kept once under the
+    /// last of them, which holds any symbols nested in the range.
+    children: Vec<Vec<usize>>,
+    /// Symbols not nested in any other, likewise
+    roots: Vec<usize>,
+}
+
+impl<'a> Version<'a> {
+    fn new(file: FileVersion<'a>) -> Self {
+        let symbols = canonical(file.symbols).into_owned();
+        let mut children = vec![Vec::new(); symbols.len()];
+        let mut roots = Vec::new();
+        let range = |i: usize| (symbols[i].start_byte, symbols[i].end_byte);
+        for (i, parent) in SemanticDiffer::parents(&symbols).into_iter().enumerate() {
+            let siblings = match parent {
+                Some(p) => &mut children[p],
+                None => &mut roots,
+            };
+            match siblings.last_mut() {
+                Some(last) if range(*last) == range(i) => *last = i,
+                _ => siblings.push(i),
+            }
+        }
+        Self {
+            content: file.content,
+            symbols,
+            children,
+            roots,
+        }
+    }
+
+    fn text(&self, symbol: usize) -> &'a [u8] {
+        let s = &self.symbols[symbol];
+        &self.content[s.start_byte..s.end_byte]
+    }
+}
+
+/// One symbol of a region, as it appears in each version.
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    symbols: [Option<usize>; 3],
+}
+
+/// How a slot was merged.
+struct Resolved {
+    /// Merged text; `None` when the symbol is gone
+    text: Option<Vec<u8>>,
+    /// Side that deleted the symbol while the other changed it
+    deleted_by: Option<usize>,
+}
+
+struct Merger<'a> {
+    versions: [Version<'a>; 3],
+    /// Index of each symbol's base version, per side
+    base_of: [Vec<Option<usize>>; 3],
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merger<'_> {
+    /// Merges one byte range per version, whose direct nested symbols are
+    /// `children`. `parent` is the base symbol the region belongs to.
+    fn merge_region(
+        &mut self,
+        ranges: [Range<usize>; 3],
+        children: [Vec<usize>; 3],
+        parent: Option<usize>,
+    ) -> SrpResult<Vec<u8>> {
+        let slots = self.slots(&children);
+        let resolved = slots
+            .iter()
+            .map(|slot| self.resolve(slot))
+            .collect::<SrpResult<Vec<Resolved>>>()?;
+
+        // Place a symbol deleted on one side but kept by the merge after its
+        // closest preceding sibling on that side, so the skeleton keeps it.
+        let mut inserts: [HashMap<Option<usize>, Vec<usize>>; 3] = Default::default();
+        for (s, r) in resolved.iter().enumerate() {
+            let Some(side) = r.deleted_by else {
+                continue;
+            };
+            let anchor = (0..s)
+                .rev()
+                .find(|&a| slots[a].symbols[BASE].is_some() && slots[a].symbols[side].is_some());
+            inserts[side].entry(anchor).or_default().push(s);
+        }
+
+        let mut slot_of: [HashMap<usize, usize>; 3] = Default::default();
+        for (s, slot) in slots.iter().enumerate() {
+            for side in [BASE, OURS, THEIRS] {
+                if let Some(i) = slot.symbols[side] {
+                    slot_of[side].insert(i, s);
+                }
+            }
+        }
+        let skeletons: Vec<Vec<u8>> = [BASE, OURS, THEIRS]
+            .into_iter()
+            .map(|side| {
+                self.skeleton(
+                    side,
+                    ranges[side].clone(),
+                    &children[side],
+                    &slot_of[side],
+                    &inserts[side],
+                    parent.is_none(),
+                )
+            })
+            .collect();
+
+        let texts: Vec<Option<&[u8]>> = resolved.iter().map(|r| r.text.as_deref()).collect();
+        let lines = skeletons.iter().map(|s| lines(s)).collect::<Vec<_>>();
+        let mut merged = Vec::new();
+        for chunk in diff3(&lines[BASE], &lines[OURS], &lines[THEIRS]) {
+            match chunk {
+                Chunk::Clean(clean) => merged.extend(clean.concat()),
+                Chunk::Conflict(ours, theirs) => {
+                    let (ours, theirs) = (ours.concat(), theirs.concat());
+                    let expand_all = |s: &[u8]| -> SrpResult<String> {
+                        let text = expand(s, &texts, &mut vec![false; texts.len()])?;
+                        Ok(String::from_utf8_lossy(&text).into_owned())
+                    };
+                    let symbol = parent.map(|p| &self.versions[BASE].symbols[p]);
+                    self.conflicts.push(MergeConflict {
+                        kind: ConflictKind::Text,
+                        symbol_name: symbol.map(|s| s.name.clone()),
+                        scope: symbol.and_then(|s| s.scope.clone()),
+                        ours: Some(expand_all(&ours)?),
+                        theirs: Some(expand_all(&theirs)?),
+                    });
+                    push_conflict(&mut merged, Some(&ours), Some(&theirs), true);
+                }
+            }
+        }
+
+        expand(&merged, &texts, &mut vec![false; texts.len()])
+    }
+
+    /// Lines up the children of a region across versions: by base symbol
+    /// where there is one, otherwise (added symbols) by `SymbolKey`.
+    fn slots(&self, children: &[Vec<usize>; 3]) -> Vec<Slot> {
+        let mut slots = Vec::new();
+        let mut of_base = HashMap::new();
+        for &b in &children[BASE] {
+            of_base.insert(b, slots.len());
+            slots.push(Slot {
+                symbols: [Some(b), None, None],
+            });
+        }
+
+        let mut added: HashMap<SymbolKey, Vec<usize>> = HashMap::new();
+        for side in [OURS, THEIRS] {
+            for &c in &children[side] {
+                let base_slot = self.base_of[side][c].and_then(|b| of_base.get(&b).copied());
+                let slot = match base_slot {
+                    Some(s) => s,
+                    None => {
+                        let key = SymbolKey::of(&self.versions[side].symbols[c]);
+                        let pending = added.entry(key).or_default();
+                        match pending
+                            .iter()
+                            .position(|&s| slots[s].symbols[side].is_none())
+                        {
+                            Some(k) => pending.remove(k),
+                            None => {
+                                slots.push(Slot::default());
+                                if side == OURS {
+                                    pending.push(slots.len() - 1);
+                                }
+                                slots.len() - 1
+                            }
+                        }
+                    }
+                };
+                slots[slot].symbols[side] = Some(c);
+            }
+        }
+        slots
+    }
+
+    fn resolve(&mut self, slot: &Slot) -> SrpResult<Resolved> {
+        let text = |side: usize| slot.symbols[side].map(|i| self.versions[side].text(i));
+        let (base, ours, theirs) = (text(BASE), text(OURS), text(THEIRS));
+        let taken = |text: Option<&[u8]>| {
+            Ok(Resolved {
+                text: text.map(<[u8]>::to_vec),
+                deleted_by: None,
+            })
+        };
+
+        let kind = match base {
+            _ if ours == theirs => return taken(ours),
+            None if ours.is_none() => return taken(theirs),
+            None if theirs.is_none() => return taken(ours),
+            None => ConflictKind::BothAdded,
+            Some(_) if ours == base => return taken(theirs),
+            Some(_) if theirs == base => return taken(ours),
+            Some(_) if ours.is_none() => ConflictKind::DeletedModified,
+            Some(_) if theirs.is_none() => ConflictKind::ModifiedDeleted,
+            Some(_) => ConflictKind::BothModified,
+        };
+
+        let [b, o, t] = slot.symbols;
+        if let (ConflictKind::BothModified, Some(b), Some(o), Some(t)) = (&kind, b, o, t) {
+            let children = [(BASE, b), (OURS, o), (THEIRS, t)]
+                .map(|(side, i)| self.versions[side].children[i].clone());
+            if children.iter().any(|c| !c.is_empty()) {
+                let ranges = [(BASE, b), (OURS, o), (THEIRS, t)].map(|(side, i)| {
+                    let s = &self.versions[side].symbols[i];
+                    s.start_byte..s.end_byte
+                });
+                return Ok(Resolved {
+                    text: Some(self.merge_region(ranges, children, Some(b))?),
+                    deleted_by: None,
+                });
+            }
+        }
+
+        let (side, i) = [(OURS, o), (THEIRS, t)]
+            .into_iter()
+            .find_map(|(side, i)| Some((side, i?)))
+            .expect("a conflict has at least one side");
+        let symbol = &self.versions[side].symbols[i];
+        let string = |text: Option<&[u8]>| text.map(|t| String::from_utf8_lossy(t).into_owned());
+        self.conflicts.push(MergeConflict {
+            kind: kind.clone(),
+            symbol_name: Some(symbol.name.clone()),
+            scope: symbol.scope.clone(),
+            ours: string(ours),
+            theirs: string(theirs),
+        });
+
+        let mut text = Vec::new();
+        push_conflict(&mut text, ours, theirs, false);
+        Ok(Resolved {
+            text: Some(text),
+            deleted_by: match kind {
+                ConflictKind::DeletedModified => Some(OURS),
+                ConflictKind::ModifiedDeleted => Some(THEIRS),
+                _ => None,
+            },
+        })
+    }
+
+    /// The region's text on one side with every child replaced by its slot
+    /// placeholder, plus placeholders `inserts`ed after a given slot (or
+    /// before the first child, under `None`).
+    fn skeleton(
+        &self,
+        side: usize,
+        range: Range<usize>,
+        children: &[usize],
+        slot_of: &HashMap<usize, usize>,
+        inserts: &HashMap<Option<usize>, Vec<usize>>,
+        top_level: bool,
+    ) -> Vec<u8> {
+        let version = &self.versions[side];
+        let leading = inserts.get(&None).map(Vec::as_slice).unwrap_or_default();
+        let mut out = Vec::new();
+        let mut pos = range.start;
+        for (k, &c) in children.iter().enumerate() {
+            let symbol = &version.symbols[c];
+            push_text(&mut out, &version.content[pos..symbol.start_byte]);
+            if k == 0 {
+                insert_lines(&mut out, leading);
+            }
+            let slot = slot_of[&c];
+            push_token(&mut out, slot);
+            for &s in inserts.get(&Some(slot)).into_iter().flatten() {
+                out.push(b'\n');
+                push_token(&mut out, s);
+            }
+            pos = symbol.end_byte;
+        }
+        push_text(&mut out, &version.content[pos..range.end]);
+
+        if children.is_empty() && !leading.is_empty() {
+            if top_level {
+                if out.last().is_some_and(|&b| b != b'\n') {
+                    out.push(b'\n');
+                }
+                let mut tail = Vec::new();
+                insert_lines(&mut tail, leading);
+                out.extend(tail);
+            } else {
+                // Before the closing line of the parent
+                insert_lines(&mut out, leading);
+            }
+        }
+        out
+    }
+}
+
+/// Inserts one placeholder line per slot at the start of the last line of `out`.
+fn insert_lines(out: &mut Vec<u8>, slots: &[usize]) {
+    if slots.is_empty() {
+        return;
+    }
+    let at = out.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let mut lines = Vec::new();
+    for &s in slots {
+        push_token(&mut lines, s);
+        lines.push(b'\n');
+    }
+    out.splice(at..at, lines);
+}
+
+fn push_token(out: &mut Vec<u8>, slot: usize) {
+    out.push(TOKEN);
+    out.extend_from_slice(slot.to_string().as_bytes());
+    out.push(TOKEN);
+}
+
+/// Appends source text to a skeleton, escaping its `TOKEN` bytes.
+fn push_text(out: &mut Vec<u8>, text: &[u8]) {
+    for &b in text {
+        out.push(b);
+        if b == TOKEN {
+            out.push(TOKEN);
+        }
+    }
+}
+
+/// Replaces placeholders with their slot's text and unescapes source
+/// `TOKEN` bytes. A slot is written out at its first placeholder only.
+fn expand(skeleton: &[u8], texts: &[Option<&[u8]>], seen: &mut [bool]) -> SrpResult<Vec<u8>> {
+    let malformed = || SrpError::Internal("Malformed placeholder in merge skeleton".into());
+    let mut out = Vec::with_capacity(skeleton.len());
+    let mut rest = skeleton;
+    while let Some(start) = rest.iter().position(|&b| b == TOKEN) {
+        out.extend_from_slice(&rest[..start]);
+        let len = rest[start + 1..]
+            .iter()
+            .position(|&b| b == TOKEN)
+            .ok_or_else(malformed)?;
+        let digits = &rest[start + 1..start + 1 + len];
+        rest = &rest[start + len + 2..];
+        if digits.is_empty() {
+            out.push(TOKEN);
+            continue;
+        }
+        let slot: usize = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|d| d.parse().ok())
+            .filter(|&slot| slot < texts.len())
+            .ok_or_else(malformed)?;
+        if !seen[slot] {
+            seen[slot] = true;
+            out.extend_from_slice(texts[slot].unwrap_or_default());
+        }
+    }
+    out.extend_from_slice(rest);
+    Ok(out)
+}
+
+fn push_conflict(out: &mut Vec<u8>, ours: Option<&[u8]>, theirs: Option<&[u8]>, lines: bool) {
+    let side = |out: &mut Vec<u8>, text: Option<&[u8]>| {
+        if let Some(text) = text.filter(|t| !t.is_empty()) {
+            out.extend_from_slice(text);
+            if !lines || !text.ends_with(b"\n") {
+                out.push(b'\n');
+            }
+        }
+    };
+    out.extend_from_slice(b"<<<<<<< ours\n");
+    side(out, ours);
+    out.extend_from_slice(b"=======\n");
+    side(out, theirs);
+    out.extend_from_slice(b">>>>>>> theirs");
+    if lines {
+        out.push(b'\n');
+    }
+}
+
+fn lines(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|&b| b == b'\n').collect()
+}
+
+enum Chunk<'a> {
+    Clean(Vec<&'a [u8]>),
+    Conflict(Vec<&'a [u8]>, Vec<&'a [u8]>),
+}
+
+/// Changed line ranges of `side` against `base`, as (base range, side range).
+fn hunks(base: &[&[u8]], side: &[&[u8]]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, base, side) {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some((o, n)) if o.end == old.start && n.end == new.start => {
+                o.end = old.end;
+                n.end = new.end;
+            }
+            _ => hunks.push((old, new)),
+        }
+    }
+    hunks
+}
+
+/// Whether a hunk touches a region of base lines. Changes that only meet at
+/// a boundary do not; two insertions at the same line do.
+fn overlaps(region: &Range<usize>, hunk: &Range<usize>) -> bool {
+    match (region.is_empty(), hunk.is_empty()) {
+        (false, false) => hunk.start < region.end && region.start < hunk.end,
+        (false, true) => region.start < hunk.start && hunk.start < region.end,
+        (true, false) => hunk.start < region.start && region.start < hunk.end,
+        (true, true) => hunk.start == region.start,
+    }
+}
+
+/// Line-based three-way merge.
+fn diff3<'a>(base: &[&'a [u8]], ours: &[&'a [u8]], theirs: &[&'a [u8]]) -> Vec<Chunk<'a>> {
+    let sides = [hunks(base, ours), hunks(base, theirs)];
+    let texts = [ours, theirs];
+    let mut next = [0, 0];
+    let mut pos = 0;
+    let mut chunks = Vec::new();
+
+    // Earliest pending hunk first; at one line, insertions go first.
+    while let Some(first) = (0..2)
+        .filter(|&s| next[s] < sides[s].len())
+        .min_by_key(|&s| {
+            let h = &sides[s][next[s]].0;
+            (h.start, h.end, s)
+        })
+    {
+        let mut region = sides[first][next[first]].0.clone();
+        let mut taken = [next[0]..next[0], next[1]..next[1]];
+        taken[first].end += 1;
+        loop {
+            let mut grown = false;
+            for s in 0..2 {
+                if let Some((hunk, _)) = sides[s].get(taken[s].end) {
+                    if overlaps(&region, hunk) {
+                        region = region.start.min(hunk.start)..region.end.max(hunk.end);
+                        taken[s].end += 1;
+                        grown = true;
+                    }
+                }
+            }
+            if !grown {
+                break;
+            }
+        }
+
+        chunks.push(Chunk::Clean(base[pos..region.start].to_vec()));
+        let side_text = |s: usize| -> Vec<&'a [u8]> {
+            if taken[s].is_empty() {
+                return base[region.clone()].to_vec();
+            }
+            let first = &sides[s][taken[s].start];
+            let last = &sides[s][taken[s].end - 1];
+            let start = first.1.start - (first.0.start - region.start);
+            let end = last.1.end + (region.end - last.0.end);
+            texts[s][start..end].to_vec()
+        };
+        let (o, t) = (side_text(0), side_text(1));
+        chunks.push(if taken[1].is_empty() || o == t {
+            Chunk::Clean(o)
+        } else if taken[0].is_empty() {
+            Chunk::Clean(t)
+        } else {
+            Chunk::Conflict(o, t)
+        });
+
+        pos = region.end;
+        next = [taken[0].end, taken[1].end];
+    }
+
+    chunks.push(Chunk::Clean(base[pos..].to_vec()));
+    chunks
+}
@@ -0,0 +1,224 @@
+#[cfg(test)]
+mod tests {
+    use crate::semantic::SemanticParser;
+    use crate::semantic::merge::{ConflictKind, MergeResult, merge_sources};
+    use bytes::Bytes;
+
+    const BASE: &str = "use std::fmt;\n\nfn parse(input: &str) -> u32 {\n    input.len() as u32\n}\nfn render(value: u32) -> String {\n    value.to_string()\n}\n";
+
+    fn merge(base: &str, ours: &str, theirs: &str) -> MergeResult {
+        merge_in("rs", base, ours, theirs)
+    }
+
+    fn merge_in(extension: &str, base: &str, ours: &str, theirs: &str) -> MergeResult {
+        let mut parser = SemanticParser::new().unwrap();
+        merge_sources(
+            &mut parser,
+            extension,
+            &Bytes::from(base.to_string()),
+            &Bytes::from(ours.to_string()),
+            &Bytes::from(theirs.to_string()),
+        )
+        .unwrap()
+    }
+
+    fn text(result: &MergeResult) -> &str {
+        std::str::from_utf8(&result.content).unwrap()
+    }
+
+    #[test]
+    fn test_merge_takes_changes_to_adjacent_functions_from_both_sides() {
+        let ours = BASE.replace("input.len() as u32", "input.trim().len() as u32");
+        let theirs = BASE.replace("value.to_string()", "format!(\"{}\", value)");
+
+        let result = merge(BASE, &ours, &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            BASE.replace("input.len() as u32", "input.trim().len() as u32")
+                .replace("value.to_string()", "format!(\"{}\", value)")
+        );
+    }
+
+    #[test]
+    fn test_merge_takes_changes_to_functions_on_neighbouring_lines() {
+        let base = "fn a() -> u8 { 1 }\nfn b() -> u8 { 2 }\n";
+
+        let result = merge(
+            base,
+            &base.replace("{ 1 }", "{ 10 }"),
+            &base.replace("{ 2 }", "{ 20 }"),
+        );
+
+        assert!(result.is_clean());
+        assert_eq!(text(&result), "fn a() -> u8 { 10 }\nfn b() -> u8 { 20 }\n");
+    }
+
+    #[test]
+    fn test_merge_reports_both_modified_symbol() {
+        let ours = BASE.replace("input.len() as u32", "0");
+        let theirs = BASE.replace("input.len() as u32", "1");
+
+        let result = merge(BASE, &ours, &theirs);
+
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.kind, ConflictKind::BothModified);
+        assert_eq!(conflict.symbol_name.as_deref(), Some("parse"));
+        assert!(conflict.ours.as_deref().unwrap().contains("    0\n"));
+        assert!(conflict.theirs.as_deref().unwrap().contains("    1\n"));
+        assert!(text(&result).contains(
+            "<<<<<<< ours\nfn parse(input: &str) -> u32 {\n    0\n}\n=======\nfn parse(input: &str) -> u32 {\n    1\n}\n>>>>>>> theirs\n"
+        ));
+        assert!(
+            text(&result)
+                .ends_with("fn render(value: u32) -> String {\n    value.to_string()\n}\n")
+        );
+    }
+
+    #[test]
+    fn test_merge_reports_delete_against_modify() {
+        let ours = BASE.replace(
+            "fn render(value: u32) -> String {\n    value.to_string()\n}\n",
+            "",
+        );
+        let theirs = BASE.replace("value.to_string()", "value.to_string() + \"!\"");
+
+        let result = merge(BASE, &ours, &theirs);
+
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::DeletedModified);
+        assert_eq!(result.conflicts[0].ours, None);
+        assert!(text(&result).contains(
+            "<<<<<<< ours\n=======\nfn render(value: u32) -> String {\n    value.to_string() + \"!\"\n}\n>>>>>>> theirs"
+        ));
+        assert_eq!(text(&result).matches("fn render").count(), 1);
+    }
+
+    #[test]
+    fn test_merge_keeps_additions_and_deletions() {
+        let ours = format!("{}\nfn added() {{}}\n", BASE);
+        let theirs = BASE.replace("use std::fmt;\n\n", "");
+
+        let result = merge(BASE, &ours, &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            format!(
+                "{}\nfn added() {{}}\n",
+                BASE.replace("use std::fmt;\n\n", "")
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_recurses_into_changed_impl() {
+        let base = "impl Point {\n    fn x(&self) -> i32 {\n        self.x\n    }\n    fn y(&self) -> i32 {\n        self.y\n    }\n}\n";
+        let ours = base.replace("self.x\n", "self.x * 2\n");
+        let theirs = base.replace("self.y\n", "self.y * 3\n");
+
+        let result = merge(base, &ours, &theirs);
+
+        assert!(result.is_clean(), "{:?}", result.conflicts);
+        assert_eq!(
+            text(&result),
+            base.replace("self.x\n", "self.x * 2\n")
+                .replace("self.y\n", "self.y * 3\n")
+        );
+    }
+
+    #[test]
+    fn test_merge_reports_same_added_symbol() {
+        let ours = format!("{}fn extra() -> u8 {{ 1 }}\n", BASE);
+        let theirs = format!("{}fn extra() -> u8 {{ 2 }}\n", BASE);
+
+        let result = merge(BASE, &ours, &theirs);
+
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::BothAdded);
+        assert_eq!(text(&result).matches("fn extra").count(), 2);
+    }
+
+    #[test]
+    fn test_merge_keeps_placeholder_bytes_of_the_source() {
+        // The merge marks symbols with \x01 internally; the source's own
+        // \x01 bytes, even ones shaped like a marker, must pass through.
+        let base = "const A: &str = \"\u{1}1\u{1}\";\nconst B: &str = \"\u{1}x\";\nconst C: &str = \"\u{1}99\u{1}\";\nfn a() -> u8 { 1 }\nfn b() -> u8 { 2 }\n";
+        let ours = base.replace("{ 1 }", "{ 10 }");
+        let theirs = base.replace("{ 2 }", "{ 20 }");
+
+        let result = merge(base, &ours, &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            base.replace("{ 1 }", "{ 10 }").replace("{ 2 }", "{ 20 }")
+        );
+
+        let result = merge(
+            base,
+            &base.replace("\u{1}x", "\u{1}y"),
+            &base.replace("\u{1}x", "\u{1}z"),
+        );
+
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::Text);
+        assert_eq!(
+            result.conflicts[0].ours.as_deref(),
+            Some("const B: &str = \"\u{1}y\";\n")
+        );
+        assert!(text(&result).starts_with("const A: &str = \"\u{1}1\u{1}\";\n<<<<<<< ours\n"));
+    }
+
+    #[test]
+    fn test_merge_go_types_declared_together() {
+        let base = "package m\n\ntype (\n\tA struct{}\n\tB int\n)\n\nfunc f() int { return 1 }\n\nfunc g() int { return 2 }\n";
+        let ours = base.replace("return 1", "return 10");
+        let theirs = base.replace("return 2", "return 20");
+
+        let result = merge_in("go", base, &ours, &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            base.replace("return 1", "return 10")
+                .replace("return 2", "return 20")
+        );
+
+        let result = merge_in("go", base, &base.replace("B int", "B uint"), &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            base.replace("B int", "B uint")
+                .replace("return 2", "return 20")
+        );
+    }
+
+    #[test]
+    fn test_merge_java_fields_declared_together() {
+        let base = "class C {\n  int a, b;\n  int f() { return 1; }\n  int g() { return 2; }\n}\n";
+        let ours = base.replace("return 1", "return 10");
+        let theirs = base.replace("return 2", "return 20");
+
+        let result = merge_in("java", base, &ours, &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            base.replace("return 1", "return 10")
+                .replace("return 2", "return 20")
+        );
+
+        let result = merge_in("java", base, &base.replace("int a, b;", "int a;"), &theirs);
+
+        assert!(result.is_clean());
+        assert_eq!(
+            text(&result),
+            base.replace("int a, b;", "int a;")
+                .replace("return 2", "return 20")
+        );
+    }
+}
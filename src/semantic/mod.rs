@@ -1,20 +1,67 @@
+pub mod assignment;
+#[cfg(test)]
+mod assignment_tests;
 pub mod chunker;
 pub mod diff;
 #[cfg(test)]
 mod diff_tests;
 pub mod fingerprint;
+pub mod merge;
+#[cfg(test)]
+mod merge_tests;
+pub mod overlap;
+#[cfg(test)]
+mod overlap_tests;
 
 pub mod queries;
+pub mod refactor;
+#[cfg(test)]
+mod refactor_tests;
 pub mod registry;
+pub mod similarity;
+pub mod tree_diff;
+#[cfg(test)]
+mod tree_diff_tests;
 
 use self::fingerprint::Fingerprinter;
 use crate::SrpResult;
-use crate::models::{SemanticSymbol, SymbolReference};
+use crate::models::{ReferenceKind, SemanticSymbol, SymbolReference};
 use bytes::Bytes;
 use std::collections::HashMap;
+use std::ops::Range;
 use streaming_iterator::StreamingIterator;
 use tree_sitter::{Node, Parser, Query, QueryCursor, Tree};
 
+/// Syntax node kinds, across the supported grammars, whose name becomes part
+/// of the scope of every symbol declared inside them.
+const SCOPE_KINDS: &[&str] = &[
+    // Rust
+    "impl_item",
+    "struct_item",
+    "enum_item",
+    "trait_item",
+    "mod_item",
+    // Python, Ruby
+    "class_definition",
+    "class",
+    "module",
+    // Java, C#, JavaScript, TypeScript, PHP
+    "class_declaration",
+    "abstract_class_declaration",
+    "interface_declaration",
+    "enum_declaration",
+    "struct_declaration",
+    "record_declaration",
+    "trait_declaration",
+    "namespace_declaration",
+    // Go
+    "type_spec",
+    // C, C++
+    "struct_specifier",
+    "class_specifier",
+    "namespace_definition",
+];
+
 pub struct SemanticParser {
     parser: Parser,
     query_cache: HashMap<String, Query>,
@@ -35,25 +82,25 @@ impl SemanticParser {
         self.tree_cache.clear();
     }
 
+    /// The names of the enclosing declarations that open a scope, outermost first.
     fn get_scope(node: Node, content: &[u8]) -> Option<String> {
         let mut current = node.parent();
         let mut parts = Vec::new();
 
         while let Some(parent) = current {
             let kind = parent.kind();
-            if matches!(
-                kind,
-                "impl_item" | "struct_item" | "class_definition" | "mod_item"
-            ) {
-                // Try to find a name node within this parent
-                for i in 0..parent.child_count() {
-                    let child = parent.child(i).expect("child index is in range");
-                    if child.kind().contains("identifier") {
-                        if let Ok(name) = child.utf8_text(content) {
-                            parts.push(name.to_string());
-                            break;
-                        }
-                    }
+            if SCOPE_KINDS.contains(&kind) {
+                // An impl is named after its type, not the trait it implements.
+                let named = parent
+                    .child_by_field_name("name")
+                    .or_else(|| parent.child_by_field_name("type"));
+                let name = named.or_else(|| {
+                    (0..parent.child_count())
+                        .filter_map(|i| parent.child(i))
+                        .find(|child| child.kind().contains("identifier"))
+                });
+                if let Some(Ok(name)) = name.map(|n| n.utf8_text(content)) {
+                    parts.push(name.to_string());
                 }
             }
             current = parent.parent();
@@ -114,27 +161,31 @@ impl SemanticParser {
         let mut references = Vec::new();
 
         while let Some(mat) = matches.next() {
-            let mut name_override: Option<String> = None;
+            // The symbol's node is captured before the name inside it, so
+            // look the name up first.
+            let mut name_override: Option<String> = mat
+                .captures
+                .iter()
+                .find(|cap| query.capture_names()[cap.index as usize].ends_with(".name"))
+                .and_then(|cap| cap.node.utf8_text(content).ok())
+                .map(str::to_string);
 
             for cap in mat.captures {
                 let node = cap.node;
                 let capture_name = &query.capture_names()[cap.index as usize];
 
                 if capture_name.ends_with(".name") {
-                    if let Ok(name) = node.utf8_text(content) {
-                        name_override = Some(name.to_string());
-                    }
                     continue;
                 }
 
                 if capture_name.starts_with("call") {
-                    if let Ok(name) = node.utf8_text(content) {
-                        references.push(SymbolReference {
-                            symbol_name: name.to_string(),
-                            snapshot_id,
-                            start_line: node.start_position().row,
-                            start_byte: node.start_byte(),
-                        });
+                    references.extend(Self::call(node, content, snapshot_id));
+                    continue;
+                }
+
+                if *capture_name == "import" {
+                    if let Some(argument) = node.child_by_field_name("argument") {
+                        Self::imports(argument, content, None, node, snapshot_id, &mut references);
                     }
                     continue;
                 }
@@ -155,32 +206,181 @@ impl SemanticParser {
                 }
 
                 let structural_hash = Fingerprinter::compute(node, content);
+                let shape = Fingerprinter::shape(node);
+                let hashes = Fingerprinter::hashes(node, content);
                 let scope = Self::get_scope(node, content);
 
-                symbols.push(SemanticSymbol {
-                    id: 0,
-                    name,
-                    kind,
-                    scope,
-                    snapshot_id,
-                    chunk_hash: "".to_string(),
-                    structural_hash,
-                    start_line: node.start_position().row,
-                    end_line: node.end_position().row,
-                    start_byte: node.start_byte(),
-                    end_byte: node.end_byte(),
-                    parent_id: None,
-                });
+                symbols.push((
+                    node,
+                    SemanticSymbol {
+                        id: 0,
+                        name,
+                        kind,
+                        scope,
+                        snapshot_id,
+                        chunk_hash: "".to_string(),
+                        structural_hash,
+                        start_line: node.start_position().row,
+                        end_line: node.end_position().row,
+                        start_byte: node.start_byte(),
+                        end_byte: node.end_byte(),
+                        parent_id: None,
+                        shape,
+                        hashes,
+                    },
+                ));
             }
         }
 
-        symbols.sort_by(|a, b| {
+        symbols.sort_by(|(_, a), (_, b)| {
             a.start_byte
                 .cmp(&b.start_byte)
                 .then(b.end_byte.cmp(&a.end_byte))
         });
 
-        Ok((symbols, references))
+        Ok((Self::nest(symbols, content), references))
+    }
+
+    /// The reference made by a `call_expression`, named after the called
+    /// function or method rather than the whole call.
+    fn call(node: Node, content: &[u8], snapshot_id: i64) -> Option<SymbolReference> {
+        let function = node.child_by_field_name("function")?;
+        let text = |n: Node| n.utf8_text(content).ok().map(str::to_string);
+        let (kind, name, qualifier) = match function.kind() {
+            "field_expression" => {
+                let receiver = function
+                    .child_by_field_name("value")
+                    .filter(|v| matches!(v.kind(), "identifier" | "self"));
+                (
+                    ReferenceKind::MethodCall,
+                    function.child_by_field_name("field")?,
+                    receiver.and_then(text),
+                )
+            }
+            "scoped_identifier" => (
+                ReferenceKind::Call,
+                function.child_by_field_name("name")?,
+                function.child_by_field_name("path").and_then(text),
+            ),
+            _ => (ReferenceKind::Call, function, None),
+        };
+        Some(SymbolReference {
+            symbol_name: text(name)?,
+            snapshot_id,
+            start_line: node.start_position().row,
+            start_byte: node.start_byte(),
+            kind,
+            qualifier,
+        })
+    }
+
+    /// One `Import` reference per name a `use` tree binds, with `prefix` the
+    /// path of the enclosing `a::{...}` group. Glob imports bind `*`.
+    fn imports(
+        tree: Node,
+        content: &[u8],
+        prefix: Option<&str>,
+        declaration: Node,
+        snapshot_id: i64,
+        out: &mut Vec<SymbolReference>,
+    ) {
+        let text = |n: Node| n.utf8_text(content).unwrap_or_default().to_string();
+        let join = |path: String| match prefix {
+            Some(prefix) => format!("{}::{}", prefix, path),
+            None => path,
+        };
+        let mut push = |name: String, path: String| {
+            out.push(SymbolReference {
+                symbol_name: name,
+                snapshot_id,
+                start_line: declaration.start_position().row,
+                start_byte: declaration.start_byte(),
+                kind: ReferenceKind::Import,
+                qualifier: Some(path),
+            })
+        };
+
+        match tree.kind() {
+            "use_list" => {
+                for i in 0..tree.named_child_count() {
+                    let child = tree.named_child(i).expect("child index is in range");
+                    Self::imports(child, content, prefix, declaration, snapshot_id, out);
+                }
+            }
+            "scoped_use_list" => {
+                let path = tree.child_by_field_name("path").map(|p| join(text(p)));
+                let prefix = path.as_deref().or(prefix);
+                if let Some(list) = tree.child_by_field_name("list") {
+                    Self::imports(list, content, prefix, declaration, snapshot_id, out);
+                }
+            }
+            "use_as_clause" => {
+                if let (Some(path), Some(alias)) = (
+                    tree.child_by_field_name("path"),
+                    tree.child_by_field_name("alias"),
+                ) {
+                    push(text(alias), join(text(path)));
+                }
+            }
+            "use_wildcard" => {
+                let path = text(tree);
+                let path = path.trim_end_matches("::*").trim_end_matches('*');
+                push("*".to_string(), join(path.to_string()));
+            }
+            "self" => {
+                if let Some(prefix) = prefix {
+                    let name = prefix.rsplit("::").next().unwrap_or(prefix);
+                    push(name.to_string(), prefix.to_string());
+                }
+            }
+            _ => {
+                let path = text(tree);
+                let name = path.rsplit("::").next().unwrap_or(&path).to_string();
+                push(name, join(path));
+            }
+        }
+    }
+
+    /// Links symbols (sorted outer before inner) to the innermost symbol
+    /// around them. Ids are provisional, 1-based positions in the result;
+    /// storage renumbers them and their `parent_id`s.
+    fn nest(symbols: Vec<(Node, SemanticSymbol)>, content: &[u8]) -> Vec<SemanticSymbol> {
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(symbols.len());
+        let mut open: Vec<usize> = Vec::new();
+        for (i, (_, symbol)) in symbols.iter().enumerate() {
+            while let Some(&top) = open.last() {
+                let outer = &symbols[top].1;
+                let contains = outer.start_byte <= symbol.start_byte
+                    && symbol.end_byte <= outer.end_byte
+                    && (outer.start_byte, outer.end_byte) != (symbol.start_byte, symbol.end_byte);
+                if contains {
+                    break;
+                }
+                open.pop();
+            }
+            parents.push(open.last().copied());
+            open.push(i);
+        }
+
+        let mut nested: Vec<Vec<Range<usize>>> = vec![Vec::new(); symbols.len()];
+        for (i, parent) in parents.iter().enumerate() {
+            if let Some(p) = *parent {
+                nested[p].push(symbols[i].0.byte_range());
+            }
+        }
+
+        symbols
+            .into_iter()
+            .zip(parents)
+            .zip(nested)
+            .enumerate()
+            .map(|(i, (((node, mut symbol), parent), nested))| {
+                symbol.id = i as i64 + 1;
+                symbol.parent_id = parent.map(|p| p as i64 + 1);
+                symbol.hashes.own = Fingerprinter::own(node, content, &nested);
+                symbol
+            })
+            .collect()
     }
 
     pub fn parse_symbols(
@@ -193,4 +393,32 @@ impl SemanticParser {
         let (symbols, _) = self.parse_semantic_data(content, extension, snapshot_id, file_path)?;
         Ok(symbols)
     }
+
+    /// Parses `content` without touching the tree cache. Unsupported
+    /// languages yield `None`.
+    pub fn parse_tree(&mut self, content: &[u8], extension: &str) -> SrpResult<Option<Tree>> {
+        let lang_info = match registry::get_language_info(extension) {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        self.parser.set_language(&lang_info.language).map_err(|e| {
+            crate::error::SrpError::Internal(format!("Error setting language: {}", e))
+        })?;
+
+        Ok(self.parser.parse(content, None))
+    }
+
+    /// Returns true if `content` contains ERROR or MISSING nodes.
+    /// Unsupported languages are never reported as broken.
+    pub fn has_syntax_errors(&mut self, content: &[u8], extension: &str) -> SrpResult<bool> {
+        if registry::get_language_info(extension).is_none() {
+            return Ok(false);
+        }
+
+        match self.parse_tree(content, extension)? {
+            Some(tree) => Ok(tree.root_node().has_error()),
+            None => Ok(true),
+        }
+    }
 }
@@ -0,0 +1,147 @@
+use crate::models::{ChangeFlag, RecordKind, SemanticRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverlapKind {
+    /// One branch renamed or moved the symbol, the other changed it in place
+    RenameModify,
+    /// One branch deleted the symbol, the other changed it
+    DeleteModify,
+    /// Both branches changed the symbol in place
+    DoubleModify,
+    /// Both branches renamed or moved the symbol, to different places
+    DoubleRename,
+}
+
+/// Where a symbol lives: file, scope and name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SymbolLocation {
+    pub path: Option<String>,
+    pub scope: Option<String>,
+    pub name: String,
+}
+
+/// What one branch did to a base symbol.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BranchChange {
+    /// The records touching the symbol, as indices into the branch's stream
+    pub records: Vec<usize>,
+    pub kinds: Vec<RecordKind>,
+    /// Where the symbol ended up; unset when the branch deleted it
+    pub location: Option<SymbolLocation>,
+    #[serde(skip)]
+    modified: bool,
+}
+
+/// A base symbol changed on both branches.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Overlap {
+    pub kind: OverlapKind,
+    /// The symbol in the common base
+    pub base: SymbolLocation,
+    pub ours: BranchChange,
+    pub theirs: BranchChange,
+}
+
+/// Finds the base symbols two branches both changed.
+///
+/// Each stream holds one branch's records since the common base, in order;
+/// it may span several snapshots. Records are followed through `Renamed`
+/// and `Moved`, so later records about the new name still count against the
+/// base symbol. A rename or move that also edited the symbol counts as a
+/// change in place too. Identical changes on both sides (the same rename,
+/// both deleting) are not overlaps, and neither are `Reordered` records or
+/// parents flagged only `ChildrenChanged`: their children overlap instead.
+/// Results come back ordered by base location.
+pub fn overlapping(ours: &[SemanticRecord], theirs: &[SemanticRecord]) -> Vec<Overlap> {
+    let mut theirs = changes(theirs);
+    let mut overlaps = Vec::new();
+    for (base, ours) in changes(ours) {
+        let Some(theirs) = theirs.remove(&base) else {
+            continue;
+        };
+        let renamed = |change: &BranchChange| change.location.as_ref() != Some(&base);
+        let kind = match (&ours.location, &theirs.location) {
+            (None, None) => continue,
+            (None, _) | (_, None) => OverlapKind::DeleteModify,
+            (Some(o), Some(t)) if o != t => {
+                if renamed(&ours) && renamed(&theirs) {
+                    OverlapKind::DoubleRename
+                } else {
+                    OverlapKind::RenameModify
+                }
+            }
+            _ if ours.modified && theirs.modified => OverlapKind::DoubleModify,
+            _ => continue,
+        };
+        overlaps.push(Overlap {
+            kind,
+            base,
+            ours,
+            theirs,
+        });
+    }
+    overlaps
+}
+
+/// What a branch did to each base symbol, by base location.
+fn changes(records: &[SemanticRecord]) -> BTreeMap<SymbolLocation, BranchChange> {
+    // current location -> base location; `None` for symbols the branch added
+    let mut base_of: HashMap<SymbolLocation, Option<SymbolLocation>> = HashMap::new();
+    let mut changes: BTreeMap<SymbolLocation, BranchChange> = BTreeMap::new();
+
+    for (i, record) in records.iter().enumerate() {
+        let old = SymbolLocation {
+            path: record.path.clone(),
+            scope: record.scope.clone(),
+            name: record.symbol_name.clone(),
+        };
+        let new = match record.kind {
+            RecordKind::Added => {
+                base_of.insert(old, None);
+                continue;
+            }
+            RecordKind::Reordered => continue,
+            RecordKind::Modified if record.flags == [ChangeFlag::ChildrenChanged] => continue,
+            RecordKind::Deleted => None,
+            RecordKind::Modified => Some(old.clone()),
+            RecordKind::Renamed | RecordKind::Moved => Some(SymbolLocation {
+                path: record.new_path.clone().or_else(|| record.path.clone()),
+                scope: match record.kind {
+                    RecordKind::Moved => record.new_scope.clone(),
+                    _ => record.scope.clone(),
+                },
+                name: record
+                    .new_name
+                    .clone()
+                    .unwrap_or_else(|| record.symbol_name.clone()),
+            }),
+        };
+
+        let base = base_of.remove(&old).unwrap_or_else(|| Some(old.clone()));
+        if let Some(new) = &new {
+            base_of.insert(new.clone(), base.clone());
+        }
+        let Some(base) = base else {
+            continue;
+        };
+        let change = changes.entry(base).or_default();
+        change.records.push(i);
+        change.kinds.push(record.kind.clone());
+        change.modified |= match record.kind {
+            RecordKind::Modified => true,
+            // The pairing saw different structure, or edits besides the name
+            RecordKind::Renamed | RecordKind::Moved => {
+                record.confidence.is_some_and(|c| c < 1.0)
+                    || record
+                        .flags
+                        .iter()
+                        .any(|f| *f != ChangeFlag::SignatureChanged)
+            }
+            _ => false,
+        };
+        change.location = new;
+    }
+    changes
+}
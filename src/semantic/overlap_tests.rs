@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{RecordKind, SemanticRecord, SemanticSymbol};
+    use crate::semantic::SemanticParser;
+    use crate::semantic::diff::SemanticDiffer;
+    use crate::semantic::overlap::{OverlapKind, overlapping};
+    use bytes::Bytes;
+
+    const BASE: &str = "fn total(items: &[u32]) -> u32 {\n    items.iter().sum()\n}\n\nfn label(total: u32) -> String {\n    format!(\"{} items\", total)\n}\n\nfn unused() -> bool {\n    false\n}\n";
+
+    fn parse(source: &str) -> Vec<SemanticSymbol> {
+        SemanticParser::new()
+            .unwrap()
+            .parse_symbols(&Bytes::from(source.to_string()), "rs", 0, None)
+            .unwrap()
+    }
+
+    /// Records for a branch that went through `versions` one snapshot each.
+    fn branch(versions: &[&str]) -> Vec<SemanticRecord> {
+        let mut records = Vec::new();
+        let mut prev = parse(BASE);
+        for (i, version) in versions.iter().enumerate() {
+            let curr = parse(version);
+            records.extend(SemanticDiffer::compare(
+                &prev,
+                &curr,
+                Some(i as i64 + 1),
+                i as i64 + 2,
+            ));
+            prev = curr;
+        }
+        records
+    }
+
+    #[test]
+    fn test_overlapping_reports_double_modify_only_for_shared_symbols() {
+        let ours = branch(&[&BASE.replace("iter().sum()", "iter().copied().sum()")]);
+        let theirs = branch(&[&BASE
+            .replace("iter().sum()", "iter().max().copied().unwrap_or(0)")
+            .replace("false", "true")]);
+
+        let overlaps = overlapping(&ours, &theirs);
+
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].kind, OverlapKind::DoubleModify);
+        assert_eq!(overlaps[0].base.name, "total");
+        assert_eq!(overlaps[0].ours.kinds, vec![RecordKind::Modified]);
+        assert_eq!(overlaps[0].theirs.kinds, vec![RecordKind::Modified]);
+    }
+
+    #[test]
+    fn test_overlapping_follows_renames() {
+        // Ours renames `label` and then edits it under the new name.
+        let renamed = BASE.replace("fn label(", "fn describe(");
+        let ours = branch(&[&renamed, &renamed.replace("{} items", "{} things")]);
+        let theirs = branch(&[&BASE.replace("{} items", "{} entries")]);
+
+        let overlaps = overlapping(&ours, &theirs);
+
+        assert_eq!(overlaps.len(), 1);
+        let overlap = &overlaps[0];
+        assert_eq!(overlap.kind, OverlapKind::RenameModify);
+        assert_eq!(overlap.base.name, "label");
+        assert_eq!(
+            overlap.ours.kinds,
+            vec![RecordKind::Renamed, RecordKind::Modified]
+        );
+        assert_eq!(overlap.ours.records, vec![0, 1]);
+        assert_eq!(
+            overlap.ours.location.as_ref().map(|l| l.name.as_str()),
+            Some("describe")
+        );
+    }
+
+    #[test]
+    fn test_overlapping_reports_same_rename_with_different_edits() {
+        let renamed = BASE.replace("fn total(", "fn sum_all(");
+        let ours = branch(&[&renamed.replace("iter().sum()", "iter().copied().sum()")]);
+        let theirs = branch(&[&renamed.replace("iter().sum()", "iter().sum::<u32>()")]);
+
+        let overlaps = overlapping(&ours, &theirs);
+
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].kind, OverlapKind::DoubleModify);
+        assert_eq!(overlaps[0].base.name, "total");
+        assert_eq!(overlaps[0].ours.kinds, vec![RecordKind::Renamed]);
+        assert_eq!(overlaps[0].theirs.kinds, vec![RecordKind::Renamed]);
+
+        // The same rename alone on both sides is no overlap.
+        assert!(overlapping(&branch(&[&renamed]), &branch(&[&renamed])).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_reports_delete_against_modify() {
+        let ours = branch(&[&BASE.replace("fn unused() -> bool {\n    false\n}\n", "")]);
+        let theirs = branch(&[&BASE.replace("false", "true")]);
+
+        let overlaps = overlapping(&ours, &theirs);
+
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].kind, OverlapKind::DeleteModify);
+        assert_eq!(overlaps[0].base.name, "unused");
+        assert!(overlaps[0].ours.location.is_none());
+        assert_eq!(overlaps[0].theirs.kinds, vec![RecordKind::Modified]);
+    }
+
+    #[test]
+    fn test_overlapping_ignores_identical_changes() {
+        let version = BASE
+            .replace("fn label(", "fn describe(")
+            .replace("fn unused() -> bool {\n    false\n}\n", "");
+
+        assert!(overlapping(&branch(&[&version]), &branch(&[&version])).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_reports_different_renames() {
+        let ours = branch(&[&BASE.replace("fn label(", "fn describe(")]);
+        let theirs = branch(&[&BASE.replace("fn label(", "fn caption(")]);
+
+        let overlaps = overlapping(&ours, &theirs);
+
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].kind, OverlapKind::DoubleRename);
+    }
+}
@@ -6,13 +6,18 @@ pub const RUST_QUERY: &str = r#"
     (mod_item name: (identifier) @module.name) @module
     (type_item name: (type_identifier) @type.name) @type
     (enum_item name: (type_identifier) @enum.name) @enum
+    (field_declaration name: (field_identifier) @field.name) @field
+    (enum_variant name: (identifier) @variant.name) @variant
     (call_expression function: (identifier) @call.name) @call
     (call_expression function: (field_expression field: (field_identifier) @call.name)) @call
+    (call_expression function: (scoped_identifier name: (identifier) @call.name)) @call
+    (use_declaration) @import
 "#;
 
 pub const PYTHON_QUERY: &str = r#"
     (function_definition name: (identifier) @function.name) @function
     (class_definition name: (identifier) @class.name) @class
+    (class_definition body: (block (expression_statement (assignment left: (identifier) @field.name) @field)))
 "#;
 
 pub const JAVASCRIPT_QUERY: &str = r#"
@@ -20,34 +25,48 @@ pub const JAVASCRIPT_QUERY: &str = r#"
     (method_definition name: (property_identifier) @method.name) @method
     (class_declaration name: (identifier) @class.name) @class
     (variable_declarator name: (identifier) @variable.name value: (arrow_function)) @function
+    (field_definition property: [
+        (property_identifier) @field.name
+        (private_property_identifier) @field.name
+    ]) @field
 "#;
 
 pub const TYPESCRIPT_QUERY: &str = r#"
     (function_declaration name: (identifier) @function.name) @function
     (method_definition name: (property_identifier) @method.name) @method
-    (class_declaration name: (identifier) @class.name) @class
+    (class_declaration name: (type_identifier) @class.name) @class
     (interface_declaration name: (type_identifier) @interface.name) @interface
     (type_alias_declaration name: (type_identifier) @type.name) @type
     (enum_declaration name: (identifier) @enum.name) @enum
+    (public_field_definition name: [
+        (property_identifier) @field.name
+        (private_property_identifier) @field.name
+    ]) @field
+    (property_signature name: (property_identifier) @field.name) @field
+    (enum_body (property_identifier) @variant.name @variant)
+    (enum_assignment name: (property_identifier) @variant.name) @variant
 "#;
 
 pub const GO_QUERY: &str = r#"
     (function_declaration name: (identifier) @function.name) @function
     (method_declaration name: (field_identifier) @method.name) @method
     (type_declaration (type_spec name: (type_identifier) @type.name)) @type
+    (field_declaration name: (field_identifier) @field.name) @field
 "#;
 
 pub const C_QUERY: &str = r#"
     (function_definition declarator: (function_declarator declarator: (identifier) @function.name)) @function
     (struct_specifier name: (type_identifier) @struct.name) @struct
     (type_definition declarator: (type_identifier) @type.name) @type
+    (field_declaration declarator: (field_identifier) @field.name) @field
 "#;
 
 pub const CPP_QUERY: &str = r#"
     (function_definition declarator: (function_declarator declarator: (identifier) @function.name)) @function
     (class_specifier name: (type_identifier) @class.name) @class
     (struct_specifier name: (type_identifier) @struct.name) @struct
-    (namespace_definition name: (identifier) @namespace.name) @namespace
+    (namespace_definition name: (namespace_identifier) @namespace.name) @namespace
+    (field_declaration declarator: (field_identifier) @field.name) @field
 "#;
 
 pub const JAVA_QUERY: &str = r#"
@@ -55,6 +74,9 @@ pub const JAVA_QUERY: &str = r#"
     (interface_declaration name: (identifier) @interface.name) @interface
     (method_declaration name: (identifier) @method.name) @method
     (constructor_declaration name: (identifier) @method.name) @method
+    (enum_declaration name: (identifier) @enum.name) @enum
+    (field_declaration declarator: (variable_declarator name: (identifier) @field.name)) @field
+    (enum_constant name: (identifier) @variant.name) @variant
 "#;
 
 pub const RUBY_QUERY: &str = r#"
@@ -79,14 +101,20 @@ pub const C_SHARP_QUERY: &str = r#"
         (identifier) @namespace.name
         (qualified_name) @namespace.name
     ]) @namespace
+    (field_declaration (variable_declaration (variable_declarator name: (identifier) @field.name))) @field
+    (property_declaration name: (identifier) @property.name) @property
+    (enum_member_declaration name: (identifier) @variant.name) @variant
 "#;
 
 pub const PHP_QUERY: &str = r#"
-    (function_definition name: (identifier) @function.name) @function
-    (method_definition name: (identifier) @method.name) @method
-    (class_declaration name: (identifier) @class.name) @class
-    (interface_declaration name: (identifier) @interface.name) @interface
-    (trait_declaration name: (identifier) @trait.name) @trait
+    (function_definition name: (name) @function.name) @function
+    (method_declaration name: (name) @method.name) @method
+    (class_declaration name: (name) @class.name) @class
+    (interface_declaration name: (name) @interface.name) @interface
+    (trait_declaration name: (name) @trait.name) @trait
+    (property_declaration (property_element (variable_name (name) @field.name))) @field
+    (enum_declaration name: (name) @enum.name) @enum
+    (enum_case name: (name) @variant.name) @variant
 "#;
 
 pub const JSON_QUERY: &str = r#"
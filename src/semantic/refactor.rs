@@ -0,0 +1,255 @@
+use crate::models::{RecordKind, SemanticRecord, SemanticSymbol};
+use crate::semantic::diff::SemanticDiffer;
+use crate::semantic::similarity::{containment, difference};
+use serde::{Deserialize, Serialize};
+
+/// Minimum share of a symbol's shape that must be found on the other side.
+const MIN_CONTAINMENT: f64 = 0.6;
+
+/// Symbols with fewer subtrees than this are too small to tell apart.
+const MIN_SHAPE: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefactoringKind {
+    /// Code left a surviving symbol for a new one
+    ExtractFunction,
+    /// A deleted symbol's code now lives in a surviving one
+    InlineFunction,
+    /// A deleted symbol's code was divided among several new ones
+    Split,
+    /// Several deleted symbols' code was combined into a new one
+    Merge,
+}
+
+/// A group of records that together make up one refactoring.
+///
+/// `sources` and `targets` index into the records passed to `detect`: code
+/// moved from the source symbols into the target symbols.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Refactoring {
+    pub kind: RefactoringKind,
+    pub sources: Vec<usize>,
+    pub targets: Vec<usize>,
+    /// Share of the moved code found on the other side, from 0.0 to 1.0
+    pub confidence: f64,
+}
+
+/// A record with the symbol versions it describes.
+struct Side<'a> {
+    record: usize,
+    old: Option<&'a SemanticSymbol>,
+    new: Option<&'a SemanticSymbol>,
+}
+
+/// Finds refactorings in the output of `SemanticDiffer::compare` for one
+/// file, by tracking subtree fingerprints (`SemanticSymbol::shape`) that
+/// moved between symbols.
+///
+/// Extract and Inline are looked for first, then Split and Merge among the
+/// records not yet used; each record takes part in at most one refactoring.
+/// A split whose largest part kept enough code to be reported as `Renamed`
+/// still counts, with that record as both source and target (likewise for
+/// merges).
+pub fn detect(
+    records: &[SemanticRecord],
+    prev_symbols: &[SemanticSymbol],
+    curr_symbols: &[SemanticSymbol],
+) -> Vec<Refactoring> {
+    let matches = SemanticDiffer::match_by_identity(prev_symbols, curr_symbols);
+    let usable = |s: &&SemanticSymbol| s.shape.len() >= MIN_SHAPE;
+
+    let mut modified = Vec::new();
+    // Renamed or Moved: the differ already paired an old and a new symbol
+    let mut paired = Vec::new();
+    let mut added = Vec::new();
+    let mut deleted = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        match record.kind {
+            RecordKind::Added => added.extend(
+                SemanticDiffer::symbol_of(curr_symbols, record)
+                    .filter(usable)
+                    .map(|s| Side {
+                        record: i,
+                        old: None,
+                        new: Some(s),
+                    }),
+            ),
+            RecordKind::Deleted => deleted.extend(
+                SemanticDiffer::symbol_of(prev_symbols, record)
+                    .filter(usable)
+                    .map(|s| Side {
+                        record: i,
+                        old: Some(s),
+                        new: None,
+                    }),
+            ),
+            RecordKind::Modified => {
+                let Some(c) = curr_symbols.iter().position(|s| {
+                    s.name == record.symbol_name
+                        && s.scope == record.scope
+                        && s.structural_hash == record.structural_hash
+                }) else {
+                    continue;
+                };
+                if let Some(&p) = matches.get(&c) {
+                    modified.push(Side {
+                        record: i,
+                        old: Some(&prev_symbols[p]),
+                        new: Some(&curr_symbols[c]),
+                    });
+                }
+            }
+            RecordKind::Renamed | RecordKind::Moved => {
+                let new_name = record.new_name.as_ref().unwrap_or(&record.symbol_name);
+                let new_scope = match record.kind {
+                    RecordKind::Moved => &record.new_scope,
+                    _ => &record.scope,
+                };
+                let old = prev_symbols
+                    .iter()
+                    .find(|s| s.name == record.symbol_name && s.scope == record.scope);
+                let new = curr_symbols.iter().find(|s| {
+                    s.name == *new_name
+                        && s.scope == *new_scope
+                        && s.structural_hash == record.structural_hash
+                });
+                if let (Some(old), Some(new)) = (old, new) {
+                    paired.push(Side {
+                        record: i,
+                        old: Some(old),
+                        new: Some(new),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut used = vec![false; records.len()];
+    let mut found = Vec::new();
+
+    // Extract: a new symbol made of code that left a surviving one.
+    for side in &added {
+        let new = side.new.expect("added symbols have a new side");
+        let best = best_host(&modified, |host| {
+            let removed = difference(&host.old?.shape, &host.new?.shape);
+            Some(containment(&new.shape, &removed))
+        });
+        if let Some((host, score)) = best {
+            used[side.record] = true;
+            found.push(Refactoring {
+                kind: RefactoringKind::ExtractFunction,
+                sources: vec![host],
+                targets: vec![side.record],
+                confidence: score,
+            });
+        }
+    }
+
+    // Inline: a deleted symbol whose code arrived in a surviving one.
+    for side in &deleted {
+        let old = side.old.expect("deleted symbols have an old side");
+        let best = best_host(&modified, |host| {
+            let arrived = difference(&host.new?.shape, &host.old?.shape);
+            Some(containment(&old.shape, &arrived))
+        });
+        if let Some((host, score)) = best {
+            used[side.record] = true;
+            found.push(Refactoring {
+                kind: RefactoringKind::InlineFunction,
+                sources: vec![side.record],
+                targets: vec![host],
+                confidence: score,
+            });
+        }
+    }
+
+    // Split: one deleted symbol covered by several new ones. When the
+    // differ paired it with the largest part, that part counts as well.
+    for side in deleted.iter().chain(&paired) {
+        if used[side.record] {
+            continue;
+        }
+        let old = side
+            .old
+            .expect("deleted and paired symbols have an old side");
+        let candidates = added
+            .iter()
+            .chain(std::iter::once(side).filter(|s| s.new.is_some()))
+            .filter_map(|c| Some((c.record, c.new?)));
+        if let Some((parts, score)) = parts_of(&old.shape, candidates, &used) {
+            for &p in &parts {
+                used[p] = true;
+            }
+            used[side.record] = true;
+            found.push(Refactoring {
+                kind: RefactoringKind::Split,
+                sources: vec![side.record],
+                targets: parts,
+                confidence: score,
+            });
+        }
+    }
+
+    // Merge: one new symbol covering several deleted ones, likewise.
+    for side in added.iter().chain(&paired) {
+        if used[side.record] {
+            continue;
+        }
+        let new = side.new.expect("added and paired symbols have a new side");
+        let candidates = deleted
+            .iter()
+            .chain(std::iter::once(side).filter(|s| s.old.is_some()))
+            .filter_map(|c| Some((c.record, c.old?)));
+        if let Some((parts, score)) = parts_of(&new.shape, candidates, &used) {
+            for &p in &parts {
+                used[p] = true;
+            }
+            used[side.record] = true;
+            found.push(Refactoring {
+                kind: RefactoringKind::Merge,
+                sources: parts,
+                targets: vec![side.record],
+                confidence: score,
+            });
+        }
+    }
+
+    found
+}
+
+/// The modified record scoring highest (at least `MIN_CONTAINMENT`).
+fn best_host(modified: &[Side], score: impl Fn(&Side) -> Option<f64>) -> Option<(usize, f64)> {
+    modified
+        .iter()
+        .filter_map(|host| Some((host.record, score(host)?)))
+        .filter(|&(_, s)| s >= MIN_CONTAINMENT)
+        .max_by(|x, y| x.1.total_cmp(&y.1).then(y.0.cmp(&x.0)))
+}
+
+/// At least two unused candidates that are each mostly made of `whole`, and
+/// together cover most of it. Returns their records, in record order, and
+/// the coverage.
+fn parts_of<'a>(
+    whole: &[u64],
+    candidates: impl Iterator<Item = (usize, &'a SemanticSymbol)>,
+    used: &[bool],
+) -> Option<(Vec<usize>, f64)> {
+    let mut parts = Vec::new();
+    let mut covered: Vec<u64> = Vec::new();
+    for (record, symbol) in candidates {
+        if used[record] || containment(&symbol.shape, whole) < MIN_CONTAINMENT {
+            continue;
+        }
+        parts.push(record);
+        covered.extend(&symbol.shape);
+    }
+    if parts.len() < 2 {
+        return None;
+    }
+
+    parts.sort_unstable();
+    covered.sort_unstable();
+    let coverage = containment(whole, &covered);
+    (coverage >= MIN_CONTAINMENT).then_some((parts, coverage))
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::SemanticSymbol;
+    use crate::semantic::SemanticParser;
+    use crate::semantic::diff::SemanticDiffer;
+    use crate::semantic::refactor::{Refactoring, RefactoringKind, detect};
+    use bytes::Bytes;
+
+    const VALIDATE: &str = "if order.items.is_empty() {
+            return Err(Error::Empty);
+        }
+        for item in &order.items {
+            if item.quantity == 0 {
+                return Err(Error::Quantity(item.id));
+            }
+        }";
+
+    const SHIP: &str = "let label = Label::new(&order.address);
+        carrier.send(label, order.weight())?;
+        notify(&order.customer, \"shipped\");";
+
+    fn parse(source: &str) -> Vec<SemanticSymbol> {
+        SemanticParser::new()
+            .unwrap()
+            .parse_symbols(&Bytes::from(source.to_string()), "rs", 0, None)
+            .unwrap()
+    }
+
+    /// Detects refactorings and resolves record indices to symbol names, old
+    /// names for sources and new names for targets.
+    fn run(old: &str, new: &str) -> Vec<(Refactoring, Vec<String>, Vec<String>)> {
+        let (prev, curr) = (parse(old), parse(new));
+        let records = SemanticDiffer::compare(&prev, &curr, Some(1), 2);
+        let old_names = |indices: &[usize]| -> Vec<String> {
+            indices
+                .iter()
+                .map(|&i| records[i].symbol_name.clone())
+                .collect()
+        };
+        let new_names = |indices: &[usize]| -> Vec<String> {
+            indices
+                .iter()
+                .map(|&i| {
+                    let record = &records[i];
+                    record
+                        .new_name
+                        .clone()
+                        .unwrap_or(record.symbol_name.clone())
+                })
+                .collect()
+        };
+        detect(&records, &prev, &curr)
+            .into_iter()
+            .map(|r| {
+                let (sources, targets) = (old_names(&r.sources), new_names(&r.targets));
+                (r, sources, targets)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_and_inline_function() {
+        let inlined = format!(
+            "fn process(order: &Order) -> Result<(), Error> {{\n{}\n{}\nOk(())\n}}\n",
+            VALIDATE, SHIP
+        );
+        let extracted = format!(
+            "fn process(order: &Order) -> Result<(), Error> {{\nvalidate(order)?;\n{}\nOk(())\n}}\n\
+             fn validate(order: &Order) -> Result<(), Error> {{\n{}\nOk(())\n}}\n",
+            SHIP, VALIDATE
+        );
+
+        let found = run(&inlined, &extracted);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.kind, RefactoringKind::ExtractFunction);
+        assert_eq!(found[0].1, vec!["process"]);
+        assert_eq!(found[0].2, vec!["validate"]);
+
+        let found = run(&extracted, &inlined);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.kind, RefactoringKind::InlineFunction);
+        assert_eq!(found[0].1, vec!["validate"]);
+        assert_eq!(found[0].2, vec!["process"]);
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        let whole = format!(
+            "fn handle(order: &Order) -> Result<(), Error> {{\n{}\n{}\nOk(())\n}}\n",
+            VALIDATE, SHIP
+        );
+        let parts = format!(
+            "fn check(order: &Order) -> Result<(), Error> {{\n{}\nOk(())\n}}\n\
+             fn ship(order: &Order) -> Result<(), Error> {{\n{}\nOk(())\n}}\n",
+            VALIDATE, SHIP
+        );
+
+        let found = run(&whole, &parts);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.kind, RefactoringKind::Split);
+        assert_eq!(found[0].1, vec!["handle"]);
+        assert_eq!(found[0].2, vec!["check", "ship"]);
+
+        let found = run(&parts, &whole);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.kind, RefactoringKind::Merge);
+        assert_eq!(found[0].1, vec!["check", "ship"]);
+        assert_eq!(found[0].2, vec!["handle"]);
+    }
+
+    #[test]
+    fn test_unrelated_changes_are_not_refactorings() {
+        let old = format!("fn process(order: &Order) {{\n{}\n}}\n", SHIP);
+        let new = format!(
+            "fn process(order: &Order) {{\n{}\nlog();\n}}\nfn greet(name: &str) {{ println!(\"{{}}\", name); }}\n",
+            SHIP
+        );
+
+        assert!(run(&old, &new).is_empty());
+    }
+}
@@ -6,10 +6,16 @@ pub struct LanguageInfo {
     pub query: &'static str,
 }
 
+/// Every extension `get_language_info` recognizes.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "go", "c", "h", "cpp", "hpp", "cc", "cxx", "java", "rb",
+    "cs", "php", "json", "html", "css", "md",
+];
+
 pub fn get_language_info(extension: &str) -> Option<LanguageInfo> {
     match extension {
         "rs" => Some(LanguageInfo {
-            language: tree_sitter_rust::language().into(),
+            language: tree_sitter_rust::language(),
             query: RUST_QUERY,
         }),
         "py" => Some(LanguageInfo {
@@ -0,0 +1,70 @@
+use crate::models::SemanticSymbol;
+
+/// Scores how alike two symbols are structurally, from 0.0 to 1.0.
+///
+/// Equal structural hashes score 1.0. Otherwise the score is the Dice
+/// coefficient of their shapes: twice the shared subtree hashes over the
+/// total. Symbols without a shape (e.g. built by hand) only match exactly.
+pub fn similarity(a: &SemanticSymbol, b: &SemanticSymbol) -> f64 {
+    if a.structural_hash == b.structural_hash {
+        return 1.0;
+    }
+    if a.shape.is_empty() || b.shape.is_empty() {
+        return 0.0;
+    }
+
+    let shared = shared_count(&a.shape, &b.shape);
+    (2 * shared) as f64 / (a.shape.len() + b.shape.len()) as f64
+}
+
+/// Scores how alike two names are, from 0.0 to 1.0, by the share of
+/// characters they have in common in order.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    similar::TextDiff::from_chars(a, b).ratio() as f64
+}
+
+/// Share of `part` that also occurs in `whole`, from 0.0 to 1.0. Both are
+/// sorted shapes compared as multisets; an empty `part` scores 0.0.
+pub fn containment(part: &[u64], whole: &[u64]) -> f64 {
+    if part.is_empty() {
+        return 0.0;
+    }
+    shared_count(part, whole) as f64 / part.len() as f64
+}
+
+/// Multiset difference `a - b` of two sorted shapes, still sorted.
+pub fn difference(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut rest = Vec::new();
+    let mut j = 0;
+    for &x in a {
+        while j < b.len() && b[j] < x {
+            j += 1;
+        }
+        if j < b.len() && b[j] == x {
+            j += 1;
+        } else {
+            rest.push(x);
+        }
+    }
+    rest
+}
+
+/// Size of the multiset intersection of two sorted slices.
+fn shared_count(a: &[u64], b: &[u64]) -> usize {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
@@ -0,0 +1,316 @@
+use crate::models::{NodeChange, NodeChangeKind};
+use blake3::{Hash, Hasher};
+use std::collections::HashMap;
+use std::ops::Range;
+use tree_sitter::Node;
+
+/// Longest text kept in a `NodeChange` snippet, in characters.
+const SNIPPET_CHARS: usize = 80;
+
+/// Minimum share of matched descendants for two unmatched containers
+/// (blocks, calls, ...) to be treated as the same node.
+const CONTAINER_SIMILARITY: f64 = 0.5;
+
+/// Computes the edit script between two versions of a symbol's syntax tree.
+///
+/// Only named nodes take part in matching; anonymous tokens (operators,
+/// keywords) make up the label of their parent, so `a + b` -> `a - b` is an
+/// update of the binary expression. Matching runs in passes: identical
+/// subtrees top-down (largest first), leftover children of matched parents by
+/// kind, containers bottom-up by their share of matched descendants, and
+/// children again below the containers found. Unmatched nodes become insertions and deletions; only the
+/// topmost node of an inserted or deleted subtree is reported.
+///
+/// Changes come in old-tree order (deletions, updates, moves), followed by
+/// insertions in new-tree order.
+pub fn diff_nodes(old: Node, old_source: &[u8], new: Node, new_source: &[u8]) -> Vec<NodeChange> {
+    let old = FlatTree::new(old, old_source);
+    let new = FlatTree::new(new, new_source);
+    let mut mapping = Mapping::new(old.nodes.len(), new.nodes.len());
+
+    // The symbols themselves are known to correspond.
+    mapping.link(0, 0);
+    match_identical(&old, &new, &mut mapping);
+    match_children(&old, &new, &mut mapping);
+    match_containers(&old, &new, &mut mapping);
+    match_children(&old, &new, &mut mapping);
+
+    edit_script(&old, &new, &mapping)
+}
+
+struct FlatNode {
+    kind: &'static str,
+    range: Range<usize>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// One past the last descendant; descendants are `index + 1..end`
+    end: usize,
+    /// Kind, text of leaves and tokens of inner nodes, plus children's hashes
+    hash: Hash,
+    /// Text of a leaf, or the anonymous tokens of an inner node
+    label: String,
+}
+
+/// Named nodes of one syntax tree in preorder.
+struct FlatTree<'a> {
+    nodes: Vec<FlatNode>,
+    source: &'a [u8],
+}
+
+impl<'a> FlatTree<'a> {
+    fn new(root: Node, source: &'a [u8]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            source,
+        };
+        tree.push(root, None);
+        tree
+    }
+
+    fn push(&mut self, node: Node, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(FlatNode {
+            kind: node.kind(),
+            range: node.byte_range(),
+            parent,
+            children: Vec::new(),
+            end: index + 1,
+            hash: Hasher::new().finalize(),
+            label: String::new(),
+        });
+
+        let mut hasher = Hasher::new();
+        hasher.update(node.kind().as_bytes());
+        let mut children = Vec::new();
+        let mut tokens = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.is_named() {
+                let c = self.push(child, Some(index));
+                hasher.update(self.nodes[c].hash.as_bytes());
+                children.push(c);
+            } else {
+                hasher.update(child.kind().as_bytes());
+                tokens.push(child.kind());
+            }
+        }
+
+        let label = if children.is_empty() {
+            self.text(node.byte_range())
+        } else {
+            tokens.join(" ")
+        };
+        hasher.update(label.as_bytes());
+
+        let end = self.nodes.len();
+        let flat = &mut self.nodes[index];
+        flat.children = children;
+        flat.end = end;
+        flat.hash = hasher.finalize();
+        flat.label = label;
+        index
+    }
+
+    fn size(&self, i: usize) -> usize {
+        self.nodes[i].end - i
+    }
+
+    fn text(&self, range: Range<usize>) -> String {
+        String::from_utf8_lossy(&self.source[range]).into_owned()
+    }
+
+    fn snippet(&self, i: usize) -> String {
+        let text = self.text(self.nodes[i].range.clone());
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        match collapsed.char_indices().nth(SNIPPET_CHARS) {
+            Some((cut, _)) => format!("{}…", &collapsed[..cut]),
+            None => collapsed,
+        }
+    }
+}
+
+struct Mapping {
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+impl Mapping {
+    fn new(old_len: usize, new_len: usize) -> Self {
+        Self {
+            old_to_new: vec![None; old_len],
+            new_to_old: vec![None; new_len],
+        }
+    }
+
+    fn link(&mut self, o: usize, n: usize) {
+        self.old_to_new[o] = Some(n);
+        self.new_to_old[n] = Some(o);
+    }
+}
+
+/// Pairs identical subtrees, largest first. A subtree is not moved out of a
+/// matched parent that still has an unmatched child of its kind. Among
+/// several identical candidates, prefer the one under the counterpart of the
+/// old parent, then one under a parent of the same kind, then the closest
+/// relative offset.
+fn match_identical(old: &FlatTree, new: &FlatTree, mapping: &mut Mapping) {
+    let mut by_hash: HashMap<Hash, Vec<usize>> = HashMap::new();
+    for (n, node) in new.nodes.iter().enumerate() {
+        by_hash.entry(node.hash).or_default().push(n);
+    }
+
+    let mut order: Vec<usize> = (0..old.nodes.len()).collect();
+    order.sort_by_key(|&o| (std::cmp::Reverse(old.size(o)), o));
+
+    let offset = |tree: &FlatTree, i: usize| tree.nodes[i].range.start - tree.nodes[0].range.start;
+    for o in order {
+        if mapping.old_to_new[o].is_some() {
+            continue;
+        }
+        let Some(candidates) = by_hash.get(&old.nodes[o].hash) else {
+            continue;
+        };
+
+        let old_parent = old.nodes[o].parent;
+        // If the parent's counterpart still has a free slot of this kind, the
+        // node most likely stayed there and was edited, rather than moved.
+        let slot_free = old_parent
+            .and_then(|p| mapping.old_to_new[p])
+            .is_some_and(|np| {
+                new.nodes[np].children.iter().any(|&c| {
+                    mapping.new_to_old[c].is_none() && new.nodes[c].kind == old.nodes[o].kind
+                })
+            });
+        let best = candidates
+            .iter()
+            .copied()
+            .filter(|&n| mapping.new_to_old[n].is_none())
+            .filter(|&n| {
+                !slot_free || old_parent.and_then(|p| mapping.old_to_new[p]) == new.nodes[n].parent
+            })
+            .min_by_key(|&n| {
+                let new_parent = new.nodes[n].parent;
+                let same_parent = old_parent.is_some()
+                    && old_parent.and_then(|p| mapping.old_to_new[p]) == new_parent;
+                let same_parent_kind =
+                    old_parent.map(|p| old.nodes[p].kind) == new_parent.map(|p| new.nodes[p].kind);
+                (
+                    !same_parent,
+                    !same_parent_kind,
+                    offset(old, o).abs_diff(offset(new, n)),
+                    n,
+                )
+            });
+
+        // Identical hashes mean identical shapes, so preorders line up.
+        if let Some(n) = best {
+            for k in 0..old.size(o) {
+                mapping.link(o + k, n + k);
+            }
+        }
+    }
+}
+
+/// Pairs unmatched inner nodes of the same kind whose descendants were
+/// mostly matched to each other. Children are visited before parents.
+fn match_containers(old: &FlatTree, new: &FlatTree, mapping: &mut Mapping) {
+    for o in (0..old.nodes.len()).rev() {
+        if mapping.old_to_new[o].is_some() || old.nodes[o].children.is_empty() {
+            continue;
+        }
+
+        // Votes for every unmatched same-kind ancestor of a matched descendant.
+        let mut votes: HashMap<usize, usize> = HashMap::new();
+        for d in o + 1..old.nodes[o].end {
+            let mut ancestor = mapping.old_to_new[d].and_then(|n| new.nodes[n].parent);
+            while let Some(n) = ancestor {
+                if mapping.new_to_old[n].is_none() && new.nodes[n].kind == old.nodes[o].kind {
+                    *votes.entry(n).or_default() += 1;
+                }
+                ancestor = new.nodes[n].parent;
+            }
+        }
+
+        let descendants = old.size(o) - 1;
+        let best = votes
+            .into_iter()
+            .map(|(n, shared)| {
+                let score = (2 * shared) as f64 / (descendants + new.size(n) - 1) as f64;
+                (score, n)
+            })
+            .filter(|&(score, _)| score >= CONTAINER_SIMILARITY)
+            .max_by(|x, y| x.0.total_cmp(&y.0).then(y.1.cmp(&x.1)));
+
+        if let Some((_, n)) = best {
+            mapping.link(o, n);
+        }
+    }
+}
+
+/// Pairs leftover children of matched parents by kind, in order. Runs in
+/// preorder, so children paired here get their own children paired too.
+fn match_children(old: &FlatTree, new: &FlatTree, mapping: &mut Mapping) {
+    for o in 0..old.nodes.len() {
+        let Some(n) = mapping.old_to_new[o] else {
+            continue;
+        };
+
+        let mut next = 0;
+        for &oc in &old.nodes[o].children {
+            if mapping.old_to_new[oc].is_some() {
+                continue;
+            }
+            let new_children = &new.nodes[n].children;
+            let found = new_children[next..].iter().position(|&nc| {
+                mapping.new_to_old[nc].is_none() && new.nodes[nc].kind == old.nodes[oc].kind
+            });
+            if let Some(offset) = found {
+                mapping.link(oc, new_children[next + offset]);
+                next += offset + 1;
+            }
+        }
+    }
+}
+
+fn edit_script(old: &FlatTree, new: &FlatTree, mapping: &Mapping) -> Vec<NodeChange> {
+    let mut changes = Vec::new();
+    let change = |kind: NodeChangeKind, o: Option<usize>, n: Option<usize>| NodeChange {
+        kind,
+        node_kind: o
+            .map(|o| old.nodes[o].kind)
+            .or(n.map(|n| new.nodes[n].kind))
+            .unwrap_or_default()
+            .to_string(),
+        old_range: o.map(|o| old.nodes[o].range.clone()),
+        new_range: n.map(|n| new.nodes[n].range.clone()),
+        old_text: o.map(|o| old.snippet(o)),
+        new_text: n.map(|n| new.snippet(n)),
+    };
+
+    for (o, node) in old.nodes.iter().enumerate() {
+        let parent_matched = node.parent.is_none_or(|p| mapping.old_to_new[p].is_some());
+        match mapping.old_to_new[o] {
+            None if parent_matched => changes.push(change(NodeChangeKind::Deleted, Some(o), None)),
+            None => {}
+            Some(n) => {
+                if node.label != new.nodes[n].label {
+                    changes.push(change(NodeChangeKind::Updated, Some(o), Some(n)));
+                }
+                if node.parent.is_some()
+                    && node.parent.and_then(|p| mapping.old_to_new[p]) != new.nodes[n].parent
+                {
+                    changes.push(change(NodeChangeKind::Moved, Some(o), Some(n)));
+                }
+            }
+        }
+    }
+
+    for (n, node) in new.nodes.iter().enumerate() {
+        let parent_matched = node.parent.is_none_or(|p| mapping.new_to_old[p].is_some());
+        if mapping.new_to_old[n].is_none() && parent_matched {
+            changes.push(change(NodeChangeKind::Inserted, None, Some(n)));
+        }
+    }
+
+    changes
+}
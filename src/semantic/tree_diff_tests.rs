@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{NodeChange, NodeChangeKind};
+    use crate::semantic::SemanticParser;
+    use crate::semantic::tree_diff::diff_nodes;
+
+    fn diff(old: &str, new: &str) -> Vec<NodeChange> {
+        let mut parser = SemanticParser::new().unwrap();
+        let old_tree = parser.parse_tree(old.as_bytes(), "rs").unwrap().unwrap();
+        let new_tree = parser.parse_tree(new.as_bytes(), "rs").unwrap().unwrap();
+        diff_nodes(
+            old_tree.root_node().named_child(0).unwrap(),
+            old.as_bytes(),
+            new_tree.root_node().named_child(0).unwrap(),
+            new.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_literal_update() {
+        let changes = diff("fn f() -> u32 { 1 }", "fn f() -> u32 { 20 }");
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, NodeChangeKind::Updated);
+        assert_eq!(changes[0].node_kind, "integer_literal");
+        assert_eq!(changes[0].old_text.as_deref(), Some("1"));
+        assert_eq!(changes[0].new_text.as_deref(), Some("20"));
+        assert_eq!(changes[0].old_range, Some(16..17));
+        assert_eq!(changes[0].new_range, Some(16..18));
+    }
+
+    #[test]
+    fn test_operator_update() {
+        let changes = diff(
+            "fn f(a: u32) -> u32 { a + 1 }",
+            "fn f(a: u32) -> u32 { a - 1 }",
+        );
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, NodeChangeKind::Updated);
+        assert_eq!(changes[0].node_kind, "binary_expression");
+    }
+
+    #[test]
+    fn test_statement_inserted_and_deleted() {
+        let old = "fn f() {\n    a();\n}";
+        let new = "fn f() {\n    a();\n    b();\n}";
+
+        let inserted = diff(old, new);
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0].kind, NodeChangeKind::Inserted);
+        assert_eq!(inserted[0].node_kind, "expression_statement");
+        assert_eq!(inserted[0].new_text.as_deref(), Some("b();"));
+        assert!(inserted[0].old_range.is_none());
+
+        let deleted = diff(new, old);
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].kind, NodeChangeKind::Deleted);
+        assert_eq!(deleted[0].old_text.as_deref(), Some("b();"));
+        assert!(deleted[0].new_range.is_none());
+    }
+
+    #[test]
+    fn test_statement_moved_into_new_block() {
+        let old = "fn f(email: Option<String>) {\n    check(&email);\n}";
+        let new = "fn f(email: Option<String>) {\n    if let Some(email) = email {\n        check(&email);\n    }\n}";
+
+        let changes = diff(old, new);
+
+        assert!(changes.iter().any(|c| {
+            c.kind == NodeChangeKind::Inserted
+                && c.node_kind == "expression_statement"
+                && c.new_text
+                    .as_deref()
+                    .is_some_and(|t| t.starts_with("if let Some(email)"))
+        }));
+        assert!(
+            changes.iter().any(|c| c.kind == NodeChangeKind::Moved
+                && c.old_text.as_deref() == Some("check(&email);"))
+        );
+        assert!(!changes.iter().any(|c| c.kind == NodeChangeKind::Deleted));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::engine::Engine;
+use crate::error::{SrpError, SrpResult};
+use crate::protocol::{
+    AnalyzeParams, AnalyzeResult, DiffParams, DiffResult, HistoryParams, HistoryResult,
+    InitializeParams, InitializeResult, RestoreFormat, RestoreParams, RestoreResult, SRP_VERSION,
+    SearchParams, SearchResult, SrpRequest, SrpResponse, SrpRpcError, error_codes, features,
+    is_compatible_version, methods,
+};
+use crate::semantic::SemanticParser;
+use crate::semantic::diff::SemanticDiffer;
+use crate::semantic::refactor;
+use crate::semantic::registry::SUPPORTED_EXTENSIONS;
+use bytes::Bytes;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::Mutex;
+
+/// JSON-RPC 2.0 dispatcher for the Semantic Registry Protocol.
+///
+/// Messages are framed like LSP: a `Content-Length` header, a blank line, then
+/// the JSON body. One server can be shared by many connections.
+///
+/// Stateless methods (`analyze`, `diff`) always work; methods that need stored
+/// history are only available when the server was built with an `Engine`.
+pub struct Server {
+    parser: Mutex<SemanticParser>,
+    engine: Option<Engine>,
+}
+
+impl Server {
+    pub fn new() -> SrpResult<Self> {
+        Ok(Self {
+            parser: Mutex::new(SemanticParser::new()?),
+            engine: None,
+        })
+    }
+
+    pub fn with_engine(engine: Engine) -> SrpResult<Self> {
+        Ok(Self {
+            engine: Some(engine),
+            ..Self::new()?
+        })
+    }
+
+    /// Handles one raw JSON message. Returns `None` for notifications.
+    pub fn handle_message(&self, raw: &[u8]) -> Option<SrpResponse> {
+        let value: serde_json::Value = match serde_json::from_slice(raw) {
+            Ok(v) => v,
+            Err(e) => {
+                return Some(SrpResponse::error(
+                    None,
+                    error_codes::PARSE_ERROR,
+                    e.to_string(),
+                ));
+            }
+        };
+
+        let id = value.get("id").and_then(|v| v.as_u64());
+        match serde_json::from_value::<SrpRequest>(value) {
+            Ok(request) => self.handle(request),
+            Err(e) => Some(SrpResponse::error(
+                id,
+                error_codes::INVALID_REQUEST,
+                e.to_string(),
+            )),
+        }
+    }
+
+    /// Routes a request to its handler. Returns `None` for notifications.
+    pub fn handle(&self, request: SrpRequest) -> Option<SrpResponse> {
+        let id = request.id;
+        if request.jsonrpc != "2.0" {
+            return Some(SrpResponse::error(
+                id,
+                error_codes::INVALID_REQUEST,
+                format!("Unsupported jsonrpc version: {}", request.jsonrpc),
+            ));
+        }
+
+        let outcome = match request.method.as_str() {
+            methods::INITIALIZE => call(request.params, |p| self.initialize(p)),
+            methods::ANALYZE => call(request.params, |p| self.analyze(p)),
+            methods::DIFF => call(request.params, |p| self.diff(p)),
+            methods::RESTORE if self.engine.is_some() => call(request.params, |p| self.restore(p)),
+            methods::GET_HISTORY if self.engine.is_some() => {
+                call(request.params, |p| self.history(p))
+            }
+            methods::SEARCH if self.engine.is_some() => call(request.params, |p| self.search(p)),
+            other => Err(SrpRpcError {
+                code: error_codes::METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", other),
+            }),
+        };
+
+        // Notifications never get a response, not even an error.
+        id?;
+        Some(match outcome {
+            Ok(result) => SrpResponse::success(id, result),
+            Err(error) => SrpResponse::error(id, error.code, error.message),
+        })
+    }
+
+    /// Optional protocol features this server instance supports.
+    pub fn features(&self) -> Vec<&'static str> {
+        let mut offered = Vec::new();
+        if self.engine.is_some() {
+            offered.push(features::HISTORY);
+            offered.push(features::SEARCH);
+        }
+        offered
+    }
+
+    fn initialize(&self, params: InitializeParams) -> SrpResult<InitializeResult> {
+        if !is_compatible_version(&params.protocol_version) {
+            return Err(SrpError::VersionMismatch(format!(
+                "client speaks {}, server speaks {}",
+                params.protocol_version, SRP_VERSION
+            )));
+        }
+
+        Ok(InitializeResult {
+            protocol_version: SRP_VERSION.to_string(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            languages: SUPPORTED_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            features: self
+                .features()
+                .into_iter()
+                .filter(|f| params.features.iter().any(|wanted| wanted == f))
+                .map(String::from)
+                .collect(),
+        })
+    }
+
+    fn analyze(&self, params: AnalyzeParams) -> SrpResult<AnalyzeResult> {
+        let content = Bytes::from(params.content);
+        let (symbols, references) = self
+            .parser
+            .lock()
+            .map_err(|_| SrpError::Internal("Parser lock poisoned".into()))?
+            .parse_semantic_data(&content, &params.extension, 0, None)?;
+        Ok(AnalyzeResult {
+            symbols,
+            references,
+        })
+    }
+
+    fn diff(&self, params: DiffParams) -> SrpResult<DiffResult> {
+        let records = SemanticDiffer::compare_with(
+            &params.base_symbols,
+            &params.target_symbols,
+            None,
+            0,
+            &params.options,
+        );
+        let refactorings = refactor::detect(&records, &params.base_symbols, &params.target_symbols);
+        Ok(DiffResult {
+            records,
+            refactorings,
+        })
+    }
+
+    fn restore(&self, params: RestoreParams) -> SrpResult<RestoreResult> {
+        let engine = self.engine()?;
+        let snapshot = engine.find_snapshot(&params.path, params.snapshot_id, params.timestamp)?;
+
+        if !params.dry_run {
+            engine.restore(&params.path, snapshot.id)?;
+            return Ok(RestoreResult {
+                path: params.path,
+                snapshot_id: snapshot.id,
+                written: true,
+                content: None,
+                diff: None,
+            });
+        }
+
+        // Results are JSON strings; altering bytes to fit would misreport the file.
+        let restored = String::from_utf8(engine.read_snapshot(snapshot.id)?.to_vec())
+            .map_err(|_| SrpError::Analysis(format!("Snapshot {} is not UTF-8", snapshot.id)))?;
+        let (content, diff) = match params.format {
+            RestoreFormat::Content => (Some(restored), None),
+            RestoreFormat::Diff => {
+                let current = match std::fs::read_to_string(&params.path) {
+                    Ok(text) => text,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                    Err(e) => return Err(e.into()),
+                };
+                (None, Some(unified_diff(&params.path, &current, &restored)))
+            }
+        };
+
+        Ok(RestoreResult {
+            path: params.path,
+            snapshot_id: snapshot.id,
+            written: false,
+            content,
+            diff,
+        })
+    }
+
+    fn history(&self, params: HistoryParams) -> SrpResult<HistoryResult> {
+        self.engine()?.history(&params)
+    }
+
+    fn search(&self, params: SearchParams) -> SrpResult<SearchResult> {
+        self.engine()?.search(&params)
+    }
+
+    fn engine(&self) -> SrpResult<&Engine> {
+        self.engine
+            .as_ref()
+            .ok_or_else(|| SrpError::Internal("No storage engine configured".into()))
+    }
+
+    /// Serves framed messages from `reader` until EOF, writing responses to `writer`.
+    pub fn serve<R: Read, W: Write>(&self, reader: R, mut writer: W) -> SrpResult<()> {
+        let mut reader = BufReader::new(reader);
+        while let Some(body) = read_message(&mut reader)? {
+            if let Some(response) = self.handle_message(&body) {
+                write_message(&mut writer, &serde_json::to_vec(&response)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn serve_stdio(&self) -> SrpResult<()> {
+        self.serve(io::stdin().lock(), io::stdout().lock())
+    }
+
+    /// Listens on a Unix domain socket, serving each connection on its own thread.
+    /// A stale socket file at `path` is replaced. Accept and connection errors
+    /// are logged to stderr without stopping the listener.
+    #[cfg(unix)]
+    pub fn serve_unix(&self, path: impl AsRef<std::path::Path>) -> SrpResult<()> {
+        use std::os::unix::net::UnixListener;
+
+        let path = path.as_ref();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                // A failed accept (e.g. out of file descriptors) only loses
+                // that connection; the daemon keeps listening.
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        eprintln!("sdpd: accept failed: {}", e);
+                        continue;
+                    }
+                };
+                scope.spawn(move || {
+                    let served = stream
+                        .try_clone()
+                        .map_err(SrpError::from)
+                        .and_then(|reader| self.serve(reader, stream));
+                    if let Err(e) = served {
+                        eprintln!("sdpd: connection failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+}
+
+fn call<P, R>(
+    params: serde_json::Value,
+    handler: impl FnOnce(P) -> SrpResult<R>,
+) -> Result<serde_json::Value, SrpRpcError>
+where
+    P: DeserializeOwned,
+    R: Serialize,
+{
+    let params = serde_json::from_value(params).map_err(|e| SrpRpcError {
+        code: error_codes::INVALID_PARAMS,
+        message: e.to_string(),
+    })?;
+    let result = handler(params).map_err(|e| SrpRpcError {
+        code: error_code(&e),
+        message: e.to_string(),
+    })?;
+    serde_json::to_value(result).map_err(|e| SrpRpcError {
+        code: error_codes::INTERNAL_ERROR,
+        message: e.to_string(),
+    })
+}
+
+/// Unified diff turning the `current` working file into `restored`.
+fn unified_diff(path: &str, current: &str, restored: &str) -> String {
+    similar::TextDiff::from_lines(current, restored)
+        .unified_diff()
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+fn error_code(error: &SrpError) -> i32 {
+    match error {
+        SrpError::VersionMismatch(_) => error_codes::VERSION_MISMATCH,
+        SrpError::NotFound(_) => error_codes::NOT_FOUND,
+        _ => error_codes::INTERNAL_ERROR,
+    }
+}
+
+/// Largest message body `read_message` accepts, so a bad header cannot make
+/// the daemon allocate unbounded memory.
+pub const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Reads one `Content-Length` framed message. Returns `None` on clean EOF.
+/// Bodies over `MAX_MESSAGE_BYTES` are rejected as `InvalidData`.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return match content_length {
+                None => Ok(None),
+                Some(_) => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "EOF inside message headers",
+                )),
+            };
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // Tolerate blank lines between messages.
+            continue;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let len = value.trim().parse::<usize>().map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Bad Content-Length: {}", e),
+                    )
+                })?;
+                if len > MAX_MESSAGE_BYTES {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Content-Length {} exceeds the {} byte limit",
+                            len, MAX_MESSAGE_BYTES
+                        ),
+                    ));
+                }
+                content_length = Some(len);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.expect("loop only exits with a length")];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, body: &[u8]) -> io::Result<()> {
+    write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+    writer.write_all(body)?;
+    writer.flush()
+}
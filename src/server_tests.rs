@@ -0,0 +1,325 @@
+#[cfg(test)]
+mod tests {
+    use crate::protocol::RestoreResult;
+    use crate::protocol::{
+        AnalyzeResult, DiffResult, InitializeResult, SRP_VERSION, SrpResponse, error_codes,
+        is_compatible_version,
+    };
+    use crate::server::{MAX_MESSAGE_BYTES, Server, read_message, write_message};
+    use crate::test_util;
+    use serde_json::json;
+    use std::io::BufReader;
+
+    fn request(server: &Server, value: serde_json::Value) -> SrpResponse {
+        server
+            .handle_message(&serde_json::to_vec(&value).unwrap())
+            .expect("request with id gets a response")
+    }
+
+    #[test]
+    fn test_analyze_and_diff() {
+        let server = Server::new().unwrap();
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "srp/analyze",
+                "params": { "content": "fn a() {}\nfn b() { a(); }", "extension": "rs" }
+            }),
+        );
+        assert_eq!(response.id, Some(1));
+        let analyzed: AnalyzeResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(analyzed.symbols.len(), 2);
+        assert_eq!(analyzed.references.len(), 1);
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "srp/diff",
+                "params": { "base_symbols": [], "target_symbols": analyzed.symbols }
+            }),
+        );
+        let diff: DiffResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(diff.records.len(), 2);
+    }
+
+    #[test]
+    fn test_initialize_handshake() {
+        let server = Server::new().unwrap();
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "srp/initialize",
+                "params": { "protocol_version": SRP_VERSION, "features": ["history"] }
+            }),
+        );
+        let init: InitializeResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(init.protocol_version, SRP_VERSION);
+        assert!(init.languages.iter().any(|l| l == "rs"));
+        // Without an engine there is no history to offer.
+        assert!(init.features.is_empty());
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "srp/initialize",
+                "params": { "protocol_version": "9.0.0" }
+            }),
+        );
+        assert!(response.result.is_none());
+        assert_eq!(response.error.unwrap().code, error_codes::VERSION_MISMATCH);
+
+        assert!(is_compatible_version("0.1.9"));
+        assert!(!is_compatible_version("0.2.0"));
+        assert!(!is_compatible_version("garbage"));
+    }
+
+    #[test]
+    fn test_initialize_negotiates_features() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+        let server = Server::with_engine(engine).unwrap();
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "srp/initialize",
+                "params": {
+                    "protocol_version": SRP_VERSION,
+                    "features": ["search", "incrementalParsing"]
+                }
+            }),
+        );
+        let init: InitializeResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(init.features, vec!["search"]);
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "srp/initialize",
+                "params": { "protocol_version": SRP_VERSION }
+            }),
+        );
+        let init: InitializeResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(init.features.is_empty());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let server = Server::new().unwrap();
+
+        let response = server.handle_message(b"{not json").unwrap();
+        assert_eq!(response.error.unwrap().code, error_codes::PARSE_ERROR);
+
+        let response = request(&server, json!({ "jsonrpc": "2.0", "id": 1 }));
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_REQUEST);
+
+        let response = request(
+            &server,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "srp/unknown" }),
+        );
+        assert_eq!(response.error.unwrap().code, error_codes::METHOD_NOT_FOUND);
+
+        let response = request(
+            &server,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "srp/analyze", "params": {} }),
+        );
+        assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "srp/unknown" });
+        assert!(
+            server
+                .handle_message(&serde_json::to_vec(&notification).unwrap())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_serve_framed_stream() {
+        let server = Server::new().unwrap();
+
+        let mut input = Vec::new();
+        for id in 1..=2 {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "srp/analyze",
+                "params": { "content": "def f(): pass", "extension": "py" }
+            });
+            write_message(&mut input, &serde_json::to_vec(&body).unwrap()).unwrap();
+        }
+
+        let mut output = Vec::new();
+        server.serve(input.as_slice(), &mut output).unwrap();
+
+        let mut reader = BufReader::new(output.as_slice());
+        for id in 1..=2 {
+            let body = read_message(&mut reader).unwrap().unwrap();
+            let response: SrpResponse = serde_json::from_slice(&body).unwrap();
+            assert_eq!(response.id, Some(id));
+            assert!(response.error.is_none());
+        }
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket_survives_bad_connection() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("sdpd.sock");
+        let server: &'static Server = Box::leak(Box::new(Server::new().unwrap()));
+        let listening = socket.clone();
+        std::thread::spawn(move || server.serve_unix(listening));
+
+        let connect = || loop {
+            match UnixStream::connect(&socket) {
+                Ok(stream) => return stream,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+
+        // A malformed header ends that connection with an error.
+        let mut bad = connect();
+        bad.write_all(b"Content-Length: nope\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(bad);
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        let mut good = connect();
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "srp/initialize",
+            "params": { "protocol_version": SRP_VERSION }
+        });
+        write_message(&mut good, &serde_json::to_vec(&body).unwrap()).unwrap();
+        let mut reader = BufReader::new(good);
+        let response: SrpResponse =
+            serde_json::from_slice(&read_message(&mut reader).unwrap().unwrap()).unwrap();
+        assert_eq!(response.id, Some(1));
+        assert!(response.error.is_none());
+    }
+
+    #[test]
+    fn test_read_message_rejects_oversized_body() {
+        for length in [
+            (MAX_MESSAGE_BYTES + 1).to_string(),
+            "100000000000000".to_string(),
+            u64::MAX.to_string(),
+        ] {
+            let input = format!("Content-Length: {}\r\n\r\n{{}}", length);
+            let mut reader = BufReader::new(input.as_bytes());
+            let error = read_message(&mut reader).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+
+        let mut reader = BufReader::new("Content-Length: 2\r\n\r\n{}".as_bytes());
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_restore_dry_run_and_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+
+        let file = dir.path().join("main.rs");
+        let path = file.to_str().unwrap();
+        engine.save(path, "fn a() {}\n").unwrap();
+        engine.save(path, "fn a() {}\nfn b() {}\n").unwrap();
+        std::fs::write(&file, "fn a() {}\nfn b() {}\n").unwrap();
+        let server = Server::with_engine(engine).unwrap();
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "srp/restore",
+                "params": { "path": path, "snapshot_id": 1, "dry_run": true, "format": "diff" }
+            }),
+        );
+        let restored: RestoreResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(!restored.written);
+        assert!(restored.diff.unwrap().contains("-fn b() {}"));
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "fn a() {}\nfn b() {}\n"
+        );
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "srp/restore",
+                "params": { "path": path, "snapshot_id": 1 }
+            }),
+        );
+        let restored: RestoreResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(restored.written);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn a() {}\n");
+
+        let response = request(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "srp/restore",
+                "params": { "path": path, "snapshot_id": 42, "dry_run": true }
+            }),
+        );
+        assert_eq!(response.error.unwrap().code, error_codes::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_restore_dry_run_rejects_non_utf8_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_util::engine(&dir);
+
+        let file = dir.path().join("main.rs");
+        let path = file.to_str().unwrap();
+        engine.save(path, &b"// caf\xe9\nfn a() {}\n"[..]).unwrap();
+        let server = Server::with_engine(engine).unwrap();
+
+        for (id, format) in [(1, "content"), (2, "diff")] {
+            let response = request(
+                &server,
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": "srp/restore",
+                    "params": { "path": path, "snapshot_id": 1, "dry_run": true, "format": format }
+                }),
+            );
+            let error = response.error.unwrap();
+            assert_eq!(error.code, error_codes::INTERNAL_ERROR);
+            assert!(error.message.contains("not UTF-8"));
+        }
+    }
+
+    #[test]
+    fn test_restore_requires_engine() {
+        let server = Server::new().unwrap();
+
+        let response = request(
+            &server,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "srp/restore", "params": { "path": "a.rs" } }),
+        );
+        assert_eq!(response.error.unwrap().code, error_codes::METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::{SrpError, SrpResult};
+use crate::semantic::chunker::Chunk;
+use bytes::Bytes;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// zstd level used for stored chunks. Level 3 is zstd's default and a good
+/// balance between speed on save and size on disk.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Number of leading hex characters used as the fan-out directory name.
+const PREFIX_LEN: usize = 2;
+
+/// Content Addressable Storage for chunks.
+///
+/// Every chunk is addressed by its BLAKE3 hash and stored exactly once under
+/// `{root}/{prefix}/{hash}`, zstd-compressed. Reads decompress the object and
+/// verify it against its address before handing it back.
+pub struct CasStore {
+    root: PathBuf,
+    chunks_written: AtomicU64,
+    chunks_deduplicated: AtomicU64,
+    logical_bytes: AtomicU64,
+    stored_bytes: AtomicU64,
+}
+
+/// Deduplication counters for a `CasStore` since it was opened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CasStats {
+    /// Chunks that were new and written to disk.
+    pub chunks_written: u64,
+    /// Chunks that were already present and skipped.
+    pub chunks_deduplicated: u64,
+    /// Uncompressed bytes submitted to the store, including duplicates.
+    pub logical_bytes: u64,
+    /// Compressed bytes actually written to disk.
+    pub stored_bytes: u64,
+}
+
+impl CasStats {
+    /// Ratio between submitted and stored bytes (higher is better).
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.stored_bytes == 0 {
+            return 0.0;
+        }
+        self.logical_bytes as f64 / self.stored_bytes as f64
+    }
+}
+
+impl CasStore {
+    /// Opens (or creates) a store rooted at `root`, typically `.sdp/cas`.
+    pub fn open(root: impl AsRef<Path>) -> SrpResult<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        Ok(Self {
+            root,
+            chunks_written: AtomicU64::new(0),
+            chunks_deduplicated: AtomicU64::new(0),
+            logical_bytes: AtomicU64::new(0),
+            stored_bytes: AtomicU64::new(0),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Computes the address of `data` (hex-encoded BLAKE3).
+    pub fn hash(data: &[u8]) -> String {
+        blake3::hash(data).to_hex().to_string()
+    }
+
+    /// Stores `data` if it is not already present and returns its hash.
+    pub fn put(&self, data: &[u8]) -> SrpResult<String> {
+        let hash = Self::hash(data);
+        let path = self.object_path(&hash)?;
+        self.logical_bytes
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+
+        if path.exists() {
+            self.chunks_deduplicated.fetch_add(1, Ordering::Relaxed);
+            return Ok(hash);
+        }
+
+        let compressed = zstd::encode_all(data, COMPRESSION_LEVEL)?;
+        let dir = path.parent().expect("object path always has a prefix dir");
+        fs::create_dir_all(dir)?;
+
+        // Write to a temporary file first so readers never observe a partial object.
+        let tmp = dir.join(format!("{}.{}.tmp", hash, uuid::Uuid::new_v4()));
+        {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&compressed)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, &path)?;
+
+        self.chunks_written.fetch_add(1, Ordering::Relaxed);
+        self.stored_bytes
+            .fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        Ok(hash)
+    }
+
+    /// Stores every chunk produced by `SemanticChunker::chunk`, returning hashes in order.
+    pub fn put_chunks(&self, chunks: &[Chunk]) -> SrpResult<Vec<String>> {
+        chunks.iter().map(|c| self.put(&c.data)).collect()
+    }
+
+    /// Reads a chunk back and checks that its content still matches `hash`.
+    pub fn get(&self, hash: &str) -> SrpResult<Bytes> {
+        let path = self.object_path(hash)?;
+        let compressed = fs::read(&path)?;
+        let data = zstd::decode_all(compressed.as_slice())?;
+
+        let actual = Self::hash(&data);
+        if actual != hash {
+            return Err(SrpError::Integrity(format!(
+                "chunk {} hashes to {}",
+                hash, actual
+            )));
+        }
+
+        Ok(Bytes::from(data))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.object_path(hash).map(|p| p.exists()).unwrap_or(false)
+    }
+
+    pub fn stats(&self) -> CasStats {
+        CasStats {
+            chunks_written: self.chunks_written.load(Ordering::Relaxed),
+            chunks_deduplicated: self.chunks_deduplicated.load(Ordering::Relaxed),
+            logical_bytes: self.logical_bytes.load(Ordering::Relaxed),
+            stored_bytes: self.stored_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn object_path(&self, hash: &str) -> SrpResult<PathBuf> {
+        let valid = hash.len() == blake3::OUT_LEN * 2
+            && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if !valid {
+            return Err(SrpError::Storage(format!("Invalid chunk hash: {}", hash)));
+        }
+        Ok(self.root.join(&hash[..PREFIX_LEN]).join(hash))
+    }
+}
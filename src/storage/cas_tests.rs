@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::SrpError;
+    use crate::semantic::chunker::SemanticChunker;
+    use crate::storage::cas::CasStore;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_put_get_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let hash = store.put(b"fn main() {}").unwrap();
+
+        assert!(store.contains(&hash));
+        assert!(dir.path().join(&hash[..2]).join(&hash).exists());
+        assert_eq!(
+            store.get(&hash).unwrap(),
+            Bytes::from_static(b"fn main() {}")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_chunks_are_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let content = Bytes::from("fn a() {}\n".repeat(5000));
+        for _ in 0..10 {
+            let chunks = SemanticChunker::chunk(content.clone(), "rs");
+            store.put_chunks(&chunks).unwrap();
+        }
+
+        let stats = store.stats();
+        assert_eq!(stats.logical_bytes, content.len() as u64 * 10);
+        assert_eq!(stats.chunks_deduplicated, stats.chunks_written * 9);
+        assert!(stats.dedup_ratio() > 10.0);
+    }
+
+    #[test]
+    fn test_corrupted_chunk_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let hash = store.put(b"original").unwrap();
+        let path = dir.path().join(&hash[..2]).join(&hash);
+        std::fs::write(&path, zstd::encode_all(&b"tampered"[..], 3).unwrap()).unwrap();
+
+        assert!(matches!(store.get(&hash), Err(SrpError::Integrity(_))));
+    }
+
+    #[test]
+    fn test_invalid_hash_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        assert!(!store.contains("../escape"));
+        assert!(matches!(store.get("../escape"), Err(SrpError::Storage(_))));
+    }
+}
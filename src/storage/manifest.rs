@@ -0,0 +1,124 @@
+use crate::error::{SrpError, SrpResult};
+use crate::semantic::chunker::{Chunk, SemanticChunker};
+use crate::storage::cas::CasStore;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// Location of one stored chunk inside a file version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkRef {
+    pub hash: String,
+    pub offset: usize,
+    pub length: usize,
+}
+
+/// Ordered list of chunks that make up one version of a file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub path: String,
+    pub snapshot_id: i64,
+    pub total_length: usize,
+    /// BLAKE3 of the whole file, checked after reassembly.
+    pub file_hash: String,
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl FileManifest {
+    /// Builds a manifest from chunks that were already written to a `CasStore`.
+    /// `hashes` must be in the same order as `chunks`.
+    pub fn from_chunks(
+        path: &str,
+        snapshot_id: i64,
+        content: &[u8],
+        chunks: &[Chunk],
+        hashes: Vec<String>,
+    ) -> Self {
+        let chunks = chunks
+            .iter()
+            .zip(hashes)
+            .map(|(chunk, hash)| ChunkRef {
+                hash,
+                offset: chunk.offset,
+                length: chunk.length,
+            })
+            .collect();
+
+        Self {
+            path: path.to_string(),
+            snapshot_id,
+            total_length: content.len(),
+            file_hash: CasStore::hash(content),
+            chunks,
+        }
+    }
+
+    /// Chunks `content`, stores every chunk and returns the resulting manifest.
+    pub fn store(
+        store: &CasStore,
+        path: &str,
+        snapshot_id: i64,
+        content: Bytes,
+        extension: &str,
+    ) -> SrpResult<Self> {
+        let chunks = SemanticChunker::chunk(content.clone(), extension);
+        let hashes = store.put_chunks(&chunks)?;
+        Ok(Self::from_chunks(
+            path,
+            snapshot_id,
+            &content,
+            &chunks,
+            hashes,
+        ))
+    }
+}
+
+/// Rebuilds the exact bytes described by `manifest` from `store`.
+///
+/// Chunks must tile the file without gaps or overlaps, and the result must
+/// match `manifest.file_hash`; anything else is reported as an integrity error.
+pub fn reassemble(store: &CasStore, manifest: &FileManifest) -> SrpResult<Bytes> {
+    let mut out = Vec::with_capacity(manifest.total_length);
+
+    for chunk in &manifest.chunks {
+        if chunk.offset != out.len() {
+            return Err(SrpError::Integrity(format!(
+                "{}: chunk {} expected at offset {}, found at {}",
+                manifest.path,
+                chunk.hash,
+                out.len(),
+                chunk.offset
+            )));
+        }
+
+        let data = store.get(&chunk.hash)?;
+        if data.len() != chunk.length {
+            return Err(SrpError::Integrity(format!(
+                "{}: chunk {} has {} bytes, manifest says {}",
+                manifest.path,
+                chunk.hash,
+                data.len(),
+                chunk.length
+            )));
+        }
+        out.extend_from_slice(&data);
+    }
+
+    if out.len() != manifest.total_length {
+        return Err(SrpError::Integrity(format!(
+            "{}: reassembled {} bytes, expected {}",
+            manifest.path,
+            out.len(),
+            manifest.total_length
+        )));
+    }
+
+    let actual = CasStore::hash(&out);
+    if actual != manifest.file_hash {
+        return Err(SrpError::Integrity(format!(
+            "{}: file hashes to {}, expected {}",
+            manifest.path, actual, manifest.file_hash
+        )));
+    }
+
+    Ok(Bytes::from(out))
+}
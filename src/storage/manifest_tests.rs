@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::SrpError;
+    use crate::storage::cas::CasStore;
+    use crate::storage::manifest::{FileManifest, reassemble};
+    use bytes::Bytes;
+
+    fn sample_source() -> Bytes {
+        let mut src = String::new();
+        for i in 0..2000 {
+            src.push_str(&format!("fn func_{i}() -> usize {{\n    {i} * 2\n}}\n\n"));
+        }
+        Bytes::from(src)
+    }
+
+    #[test]
+    fn test_reassemble_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+        let content = sample_source();
+
+        let manifest = FileManifest::store(&store, "src/lib.rs", 7, content.clone(), "rs").unwrap();
+
+        assert!(manifest.chunks.len() > 1);
+        assert_eq!(manifest.total_length, content.len());
+        assert_eq!(reassemble(&store, &manifest).unwrap(), content);
+    }
+
+    #[test]
+    fn test_manifest_serde_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let manifest = FileManifest::store(&store, "a.py", 1, sample_source(), "py").unwrap();
+        let json = serde_json::to_string(&manifest).unwrap();
+        let parsed: FileManifest = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_reassemble_detects_wrong_file_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let mut manifest =
+            FileManifest::store(&store, "src/lib.rs", 1, sample_source(), "rs").unwrap();
+        manifest.file_hash = CasStore::hash(b"something else");
+
+        assert!(matches!(
+            reassemble(&store, &manifest),
+            Err(SrpError::Integrity(_))
+        ));
+    }
+
+    #[test]
+    fn test_reassemble_detects_gap() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CasStore::open(dir.path()).unwrap();
+
+        let mut manifest =
+            FileManifest::store(&store, "src/lib.rs", 1, sample_source(), "rs").unwrap();
+        manifest.chunks.remove(0);
+
+        assert!(matches!(
+            reassemble(&store, &manifest),
+            Err(SrpError::Integrity(_))
+        ));
+    }
+}
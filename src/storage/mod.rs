@@ -0,0 +1,13 @@
+pub mod cas;
+#[cfg(test)]
+mod cas_tests;
+pub mod manifest;
+#[cfg(test)]
+mod manifest_tests;
+pub mod symbols;
+#[cfg(test)]
+mod symbols_tests;
+
+pub use cas::{CasStats, CasStore};
+pub use manifest::{ChunkRef, FileManifest, reassemble};
+pub use symbols::{SnapshotData, SymbolRegistry};
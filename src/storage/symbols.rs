@@ -0,0 +1,302 @@
+use crate::error::SrpResult;
+use crate::models::{SemanticRecord, SemanticSymbol, Snapshot, SymbolReference};
+use crate::storage::manifest::FileManifest;
+use chrono::Utc;
+use redb::{
+    Database, MultimapTableDefinition, ReadableMultimapTable, ReadableTable, TableDefinition,
+    WriteTransaction,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Monotonic id counters, keyed by entity name.
+const COUNTERS: TableDefinition<&str, i64> = TableDefinition::new("counters");
+const SNAPSHOTS: TableDefinition<i64, &[u8]> = TableDefinition::new("snapshots");
+const SYMBOLS: TableDefinition<i64, &[u8]> = TableDefinition::new("symbols");
+const RECORDS: TableDefinition<i64, &[u8]> = TableDefinition::new("records");
+const MANIFESTS: TableDefinition<i64, &[u8]> = TableDefinition::new("manifests");
+/// References keyed by (snapshot id, position) so they come back in source order.
+const REFERENCES: TableDefinition<(i64, u64), &[u8]> = TableDefinition::new("references");
+
+const PATH_SNAPSHOTS: MultimapTableDefinition<&str, i64> =
+    MultimapTableDefinition::new("path_snapshots");
+const SNAPSHOT_SYMBOLS: MultimapTableDefinition<i64, i64> =
+    MultimapTableDefinition::new("snapshot_symbols");
+/// Records keyed by (path, symbol name), under both names for renames.
+const SYMBOL_RECORDS: MultimapTableDefinition<(&str, &str), i64> =
+    MultimapTableDefinition::new("path_symbol_records");
+const SNAPSHOT_RECORDS: MultimapTableDefinition<i64, i64> =
+    MultimapTableDefinition::new("snapshot_records");
+
+/// Everything produced by analyzing one file version, committed together.
+#[derive(Default)]
+pub struct SnapshotData {
+    pub symbols: Vec<SemanticSymbol>,
+    pub references: Vec<SymbolReference>,
+    pub records: Vec<SemanticRecord>,
+    pub manifest: Option<FileManifest>,
+}
+
+/// Embedded symbol history database (`.sdp/db/symbols.db`), backed by redb.
+///
+/// Ids are allocated from persistent counters, so once a snapshot, symbol or
+/// record is committed its id never changes. Values are stored as JSON.
+pub struct SymbolRegistry {
+    db: Database,
+}
+
+impl SymbolRegistry {
+    /// Opens (or creates) the registry file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> SrpResult<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let db = Database::create(path)?;
+
+        // Create every table up front so read transactions never hit a missing table.
+        let txn = db.begin_write()?;
+        txn.open_table(COUNTERS)?;
+        txn.open_table(SNAPSHOTS)?;
+        txn.open_table(SYMBOLS)?;
+        txn.open_table(RECORDS)?;
+        txn.open_table(REFERENCES)?;
+        txn.open_table(MANIFESTS)?;
+        txn.open_multimap_table(PATH_SNAPSHOTS)?;
+        txn.open_multimap_table(SNAPSHOT_SYMBOLS)?;
+        txn.open_multimap_table(SYMBOL_RECORDS)?;
+        txn.open_multimap_table(SNAPSHOT_RECORDS)?;
+        txn.commit()?;
+
+        Ok(Self { db })
+    }
+
+    /// Stores a new snapshot of `path` with its symbols, references, records and
+    /// chunk manifest in a single transaction.
+    ///
+    /// Ids are assigned here: every `snapshot_id` / `to_snapshot_id` (including the
+    /// manifest's) points at the new snapshot, and every symbol and record
+    /// receives a fresh id. The stored values are written back into `data` so
+    /// callers can keep using them.
+    pub fn commit_snapshot(
+        &self,
+        path: &str,
+        file_hash: &str,
+        data: &mut SnapshotData,
+    ) -> SrpResult<Snapshot> {
+        let txn = self.db.begin_write()?;
+
+        let snapshot = Snapshot {
+            id: Self::next_id(&txn, "snapshot")?,
+            path: path.to_string(),
+            timestamp: Utc::now(),
+            file_hash: file_hash.to_string(),
+        };
+
+        {
+            let mut snapshots = txn.open_table(SNAPSHOTS)?;
+            snapshots.insert(snapshot.id, to_json(&snapshot)?.as_slice())?;
+            let mut path_snapshots = txn.open_multimap_table(PATH_SNAPSHOTS)?;
+            path_snapshots.insert(path, snapshot.id)?;
+
+            if let Some(manifest) = data.manifest.as_mut() {
+                manifest.snapshot_id = snapshot.id;
+                let mut manifests = txn.open_table(MANIFESTS)?;
+                manifests.insert(snapshot.id, to_json(manifest)?.as_slice())?;
+            }
+
+            let mut symbols = txn.open_table(SYMBOLS)?;
+            let mut snapshot_symbols = txn.open_multimap_table(SNAPSHOT_SYMBOLS)?;
+            // Parsed symbols carry provisional ids; `parent_id` follows the renumbering.
+            let mut ids = HashMap::new();
+            for symbol in data.symbols.iter_mut() {
+                let id = Self::next_id(&txn, "symbol")?;
+                ids.insert(symbol.id, id);
+                symbol.id = id;
+            }
+            for symbol in data.symbols.iter_mut() {
+                symbol.parent_id = symbol.parent_id.and_then(|p| ids.get(&p).copied());
+                symbol.snapshot_id = snapshot.id;
+                symbols.insert(symbol.id, to_json(symbol)?.as_slice())?;
+                snapshot_symbols.insert(snapshot.id, symbol.id)?;
+            }
+
+            let mut references = txn.open_table(REFERENCES)?;
+            for (i, reference) in data.references.iter_mut().enumerate() {
+                reference.snapshot_id = snapshot.id;
+                references.insert((snapshot.id, i as u64), to_json(reference)?.as_slice())?;
+            }
+
+            let mut records = txn.open_table(RECORDS)?;
+            let mut symbol_records = txn.open_multimap_table(SYMBOL_RECORDS)?;
+            let mut snapshot_records = txn.open_multimap_table(SNAPSHOT_RECORDS)?;
+            for record in data.records.iter_mut() {
+                record.id = Self::next_id(&txn, "record")?;
+                record.to_snapshot_id = snapshot.id;
+                records.insert(record.id, to_json(record)?.as_slice())?;
+                snapshot_records.insert(snapshot.id, record.id)?;
+                symbol_records.insert((path, record.symbol_name.as_str()), record.id)?;
+                if let Some(new_name) = &record.new_name {
+                    symbol_records.insert((path, new_name.as_str()), record.id)?;
+                }
+            }
+        }
+
+        txn.commit()?;
+        Ok(snapshot)
+    }
+
+    pub fn get_snapshot(&self, id: i64) -> SrpResult<Option<Snapshot>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(SNAPSHOTS)?;
+        match table.get(id)? {
+            Some(value) => Ok(Some(from_json(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All snapshots of `path`, oldest first.
+    pub fn snapshots_for_path(&self, path: &str) -> SrpResult<Vec<Snapshot>> {
+        let txn = self.db.begin_read()?;
+        let index = txn.open_multimap_table(PATH_SNAPSHOTS)?;
+        let table = txn.open_table(SNAPSHOTS)?;
+
+        let mut snapshots = Vec::new();
+        for id in index.get(path)? {
+            if let Some(value) = table.get(id?.value())? {
+                snapshots.push(from_json(value.value())?);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Every path with at least one snapshot, sorted.
+    pub fn paths(&self) -> SrpResult<Vec<String>> {
+        let txn = self.db.begin_read()?;
+        let index = txn.open_multimap_table(PATH_SNAPSHOTS)?;
+
+        let mut paths = Vec::new();
+        for entry in index.iter()? {
+            let (path, _) = entry?;
+            paths.push(path.value().to_string());
+        }
+        Ok(paths)
+    }
+
+    pub fn latest_snapshot(&self, path: &str) -> SrpResult<Option<Snapshot>> {
+        Ok(self.snapshots_for_path(path)?.pop())
+    }
+
+    /// The chunk manifest needed to rebuild the file at `snapshot_id`.
+    pub fn get_manifest(&self, snapshot_id: i64) -> SrpResult<Option<FileManifest>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(MANIFESTS)?;
+        match table.get(snapshot_id)? {
+            Some(value) => Ok(Some(from_json(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_symbol(&self, id: i64) -> SrpResult<Option<SemanticSymbol>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(SYMBOLS)?;
+        match table.get(id)? {
+            Some(value) => Ok(Some(from_json(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All symbols captured in snapshot `snapshot_id`, in source order.
+    pub fn symbols_in_snapshot(&self, snapshot_id: i64) -> SrpResult<Vec<SemanticSymbol>> {
+        let txn = self.db.begin_read()?;
+        let index = txn.open_multimap_table(SNAPSHOT_SYMBOLS)?;
+        let table = txn.open_table(SYMBOLS)?;
+
+        let mut symbols = Vec::new();
+        for id in index.get(snapshot_id)? {
+            if let Some(value) = table.get(id?.value())? {
+                symbols.push(from_json(value.value())?);
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub fn references_in_snapshot(&self, snapshot_id: i64) -> SrpResult<Vec<SymbolReference>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(REFERENCES)?;
+
+        let mut references = Vec::new();
+        for entry in table.range((snapshot_id, 0)..=(snapshot_id, u64::MAX))? {
+            let (_, value) = entry?;
+            references.push(from_json(value.value())?);
+        }
+        Ok(references)
+    }
+
+    /// All records saved with `path` whose old or new name is `symbol_name`,
+    /// oldest first.
+    pub fn records_for_symbol(
+        &self,
+        path: &str,
+        symbol_name: &str,
+    ) -> SrpResult<Vec<SemanticRecord>> {
+        let txn = self.db.begin_read()?;
+        let index = txn.open_multimap_table(SYMBOL_RECORDS)?;
+        let table = txn.open_table(RECORDS)?;
+
+        let mut records = Vec::new();
+        for id in index.get((path, symbol_name))? {
+            if let Some(value) = table.get(id?.value())? {
+                records.push(from_json(value.value())?);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Records produced by the save that created `snapshot_id`.
+    pub fn records_in_snapshot(&self, snapshot_id: i64) -> SrpResult<Vec<SemanticRecord>> {
+        let txn = self.db.begin_read()?;
+        let index = txn.open_multimap_table(SNAPSHOT_RECORDS)?;
+        let table = txn.open_table(RECORDS)?;
+
+        let mut records = Vec::new();
+        for id in index.get(snapshot_id)? {
+            if let Some(value) = table.get(id?.value())? {
+                records.push(from_json(value.value())?);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Every stored record, oldest first.
+    pub fn records(&self) -> SrpResult<Vec<SemanticRecord>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(RECORDS)?;
+
+        let mut records = Vec::new();
+        for entry in table.iter()? {
+            let (_, value) = entry?;
+            records.push(from_json(value.value())?);
+        }
+        Ok(records)
+    }
+
+    fn next_id(txn: &WriteTransaction, entity: &str) -> SrpResult<i64> {
+        let mut counters = txn.open_table(COUNTERS)?;
+        let next = counters.get(entity)?.map(|v| v.value()).unwrap_or(0) + 1;
+        counters.insert(entity, next)?;
+        Ok(next)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> SrpResult<Vec<u8>> {
+    Ok(serde_json::to_vec(value)?)
+}
+
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> SrpResult<T> {
+    Ok(serde_json::from_slice(bytes)?)
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{RecordKind, SemanticRecord, SemanticSymbol, SymbolReference};
+    use crate::storage::symbols::{SnapshotData, SymbolRegistry};
+
+    fn create_mock_symbol(name: &str, hash: &str) -> SemanticSymbol {
+        SemanticSymbol {
+            id: 0,
+            name: name.to_string(),
+            kind: "function".to_string(),
+            scope: None,
+            snapshot_id: 0,
+            chunk_hash: "".to_string(),
+            structural_hash: hash.to_string(),
+            start_line: 0,
+            end_line: 0,
+            start_byte: 0,
+            end_byte: 0,
+            parent_id: None,
+            shape: vec![],
+            hashes: Default::default(),
+        }
+    }
+
+    fn create_mock_record(name: &str, new_name: Option<&str>, kind: RecordKind) -> SemanticRecord {
+        SemanticRecord {
+            id: 0,
+            project_id: None,
+            from_snapshot_id: None,
+            to_snapshot_id: 0,
+            symbol_name: name.to_string(),
+            new_name: new_name.map(str::to_string),
+            scope: None,
+            new_scope: None,
+            parent: None,
+            path: None,
+            new_path: None,
+            confidence: None,
+            kind,
+            structural_hash: "h".to_string(),
+            flags: vec![],
+            changes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_commit_assigns_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = SymbolRegistry::open(dir.path().join("db/symbols.db")).unwrap();
+
+        let mut data = SnapshotData {
+            symbols: vec![create_mock_symbol("a", "h1"), create_mock_symbol("b", "h2")],
+            references: vec![SymbolReference {
+                symbol_name: "a".to_string(),
+                snapshot_id: 0,
+                start_line: 3,
+                start_byte: 40,
+                kind: Default::default(),
+                qualifier: None,
+            }],
+            records: vec![create_mock_record("a", None, RecordKind::Added)],
+            manifest: None,
+        };
+        let snapshot = registry
+            .commit_snapshot("src/lib.rs", "fh", &mut data)
+            .unwrap();
+
+        assert_eq!(snapshot.id, 1);
+        assert_eq!(data.symbols[0].id, 1);
+        assert_eq!(data.symbols[1].id, 2);
+        assert!(data.symbols.iter().all(|s| s.snapshot_id == snapshot.id));
+        assert_eq!(data.records[0].to_snapshot_id, snapshot.id);
+
+        let stored = registry.symbols_in_snapshot(snapshot.id).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].name, "b");
+        assert_eq!(registry.get_symbol(2).unwrap().unwrap().name, "b");
+        assert_eq!(
+            registry.references_in_snapshot(snapshot.id).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_commit_renumbers_parent_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = SymbolRegistry::open(dir.path().join("db/symbols.db")).unwrap();
+        let nested = || {
+            let mut parent = create_mock_symbol("User", "h1");
+            parent.id = 1;
+            let mut child = create_mock_symbol("email", "h2");
+            child.id = 2;
+            child.parent_id = Some(1);
+            vec![parent, child]
+        };
+
+        for _ in 0..2 {
+            let mut data = SnapshotData {
+                symbols: nested(),
+                references: vec![],
+                records: vec![],
+                manifest: None,
+            };
+            registry
+                .commit_snapshot("src/lib.rs", "fh", &mut data)
+                .unwrap();
+        }
+
+        let stored = registry.symbols_in_snapshot(2).unwrap();
+        assert_eq!(stored[0].id, 3);
+        assert_eq!(stored[0].parent_id, None);
+        assert_eq!(stored[1].parent_id, Some(3));
+    }
+
+    #[test]
+    fn test_ids_persist_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("symbols.db");
+
+        {
+            let registry = SymbolRegistry::open(&path).unwrap();
+            let mut data = SnapshotData {
+                symbols: vec![create_mock_symbol("a", "h1")],
+                ..Default::default()
+            };
+            registry.commit_snapshot("a.rs", "fh1", &mut data).unwrap();
+        }
+
+        let registry = SymbolRegistry::open(&path).unwrap();
+        let mut data = SnapshotData {
+            symbols: vec![create_mock_symbol("a", "h1")],
+            ..Default::default()
+        };
+        let snapshot = registry.commit_snapshot("a.rs", "fh2", &mut data).unwrap();
+
+        assert_eq!(snapshot.id, 2);
+        assert_eq!(data.symbols[0].id, 2);
+        let history = registry.snapshots_for_path("a.rs").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            registry.latest_snapshot("a.rs").unwrap().unwrap().file_hash,
+            "fh2"
+        );
+    }
+
+    #[test]
+    fn test_records_for_symbol_follow_new_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = SymbolRegistry::open(dir.path().join("symbols.db")).unwrap();
+
+        let mut first = SnapshotData {
+            records: vec![create_mock_record("old", None, RecordKind::Added)],
+            ..Default::default()
+        };
+        registry.commit_snapshot("a.rs", "1", &mut first).unwrap();
+
+        let mut second = SnapshotData {
+            records: vec![create_mock_record("old", Some("new"), RecordKind::Renamed)],
+            ..Default::default()
+        };
+        registry.commit_snapshot("a.rs", "2", &mut second).unwrap();
+
+        let mut other = SnapshotData {
+            records: vec![create_mock_record("old", None, RecordKind::Added)],
+            ..Default::default()
+        };
+        registry.commit_snapshot("b.rs", "3", &mut other).unwrap();
+
+        assert_eq!(registry.records_for_symbol("a.rs", "old").unwrap().len(), 2);
+        assert_eq!(registry.records_for_symbol("b.rs", "old").unwrap().len(), 1);
+        let renamed = registry.records_for_symbol("a.rs", "new").unwrap();
+        assert_eq!(renamed.len(), 1);
+        assert!(matches!(renamed[0].kind, RecordKind::Renamed));
+        assert!(
+            registry
+                .records_for_symbol("b.rs", "new")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            registry
+                .records_for_symbol("a.rs", "missing")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Fixtures shared by the `*_tests` modules.
+
+use crate::engine::{Config, Engine};
+
+/// An engine storing under `dir`, outside any project.
+pub(crate) fn engine(dir: &tempfile::TempDir) -> Engine {
+    Engine::new(Config {
+        root: dir.path().join(".sdp"),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// An engine that has saved each of `versions` of `path`, in order.
+pub(crate) fn engine_with_versions(
+    dir: &tempfile::TempDir,
+    path: &str,
+    versions: &[&str],
+) -> Engine {
+    let engine = engine(dir);
+    for version in versions {
+        engine.save(path, version.to_string()).unwrap();
+    }
+    engine
+}